@@ -0,0 +1,234 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde_json::Value;
+
+use crate::models::{AccuracyBaseline, LabeledCorpus};
+use crate::services::category_detection::detect_category;
+use crate::services::language_packs::resolve_locale;
+
+use super::{flag_value, has_flag};
+
+const DEFAULT_CORPUS_PATH: &str = "corpus/category_corpus.json";
+const DEFAULT_BASELINE_PATH: &str = "corpus/accuracy_baseline.json";
+const NO_CATEGORY: &str = "none";
+
+struct Evaluation {
+    labels: Vec<String>,
+    confusion: BTreeMap<(String, String), usize>,
+    total: usize,
+    correct: usize,
+}
+
+fn load_corpus(path: &str) -> Result<LabeledCorpus, String> {
+    std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read corpus {}: {}", path, e))
+        .and_then(|content| {
+            serde_json::from_str(&content).map_err(|e| format!("Invalid corpus {}: {}", path, e))
+        })
+}
+
+fn load_baseline(path: &str) -> Option<AccuracyBaseline> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
+
+fn evaluate(corpus: &LabeledCorpus) -> Evaluation {
+    let patterns_by_country: HashMap<Option<String>, Value> = corpus
+        .samples
+        .iter()
+        .map(|s| s.country.clone().or_else(|| corpus.default_country.clone()))
+        .map(|country| {
+            let patterns = resolve_locale(0.0, 0.0, country.as_deref()).patterns;
+            (country, patterns)
+        })
+        .collect();
+
+    let pairs: Vec<(String, String)> = corpus
+        .samples
+        .iter()
+        .map(|sample| {
+            let country = sample.country.clone().or_else(|| corpus.default_country.clone());
+            let patterns = &patterns_by_country[&country];
+            let name = sample
+                .name
+                .clone()
+                .or_else(|| sample.tags.get("name").cloned())
+                .unwrap_or_default();
+            let predicted = detect_category(patterns, &sample.tags, &name).unwrap_or(NO_CATEGORY);
+            let expected = sample.expected.as_deref().unwrap_or(NO_CATEGORY);
+            (expected.to_string(), predicted.to_string())
+        })
+        .collect();
+
+    let labels: Vec<String> = pairs
+        .iter()
+        .flat_map(|(e, p)| [e.clone(), p.clone()])
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    let confusion = pairs.iter().fold(BTreeMap::new(), |mut acc, pair| {
+        *acc.entry(pair.clone()).or_insert(0) += 1;
+        acc
+    });
+
+    Evaluation {
+        labels,
+        total: pairs.len(),
+        correct: pairs.iter().filter(|(e, p)| e == p).count(),
+        confusion,
+    }
+}
+
+fn count_where(eval: &Evaluation, predicate: impl Fn(&str, &str) -> bool) -> usize {
+    eval.confusion
+        .iter()
+        .filter(|((e, p), _)| predicate(e, p))
+        .map(|(_, n)| *n)
+        .sum()
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+fn print_report(eval: &Evaluation) {
+    println!("{:<15} {:>9} {:>9} {:>9}", "category", "precision", "recall", "support");
+    eval.labels.iter().for_each(|label| {
+        let tp = count_where(eval, |e, p| e == label && p == label);
+        let predicted = count_where(eval, |_, p| p == label);
+        let support = count_where(eval, |e, _| e == label);
+        println!(
+            "{:<15} {:>9.3} {:>9.3} {:>9}",
+            label,
+            ratio(tp, predicted),
+            ratio(tp, support),
+            support
+        );
+    });
+
+    println!();
+    println!("Confusion matrix (rows = expected, columns = predicted):");
+    let header: Vec<String> = eval
+        .labels
+        .iter()
+        .map(|l| format!("{:>7}", l.chars().take(6).collect::<String>()))
+        .collect();
+    println!("{:<15}{}", "", header.join(""));
+    eval.labels.iter().for_each(|expected| {
+        let row: Vec<String> = eval
+            .labels
+            .iter()
+            .map(|predicted| {
+                let n = eval
+                    .confusion
+                    .get(&(expected.clone(), predicted.clone()))
+                    .copied()
+                    .unwrap_or(0);
+                format!("{:>7}", n)
+            })
+            .collect();
+        println!("{:<15}{}", expected, row.join(""));
+    });
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let corpus_path = flag_value(args, "--corpus").unwrap_or(DEFAULT_CORPUS_PATH);
+    let baseline_path = flag_value(args, "--baseline").unwrap_or(DEFAULT_BASELINE_PATH);
+
+    let corpus = match load_corpus(corpus_path) {
+        Ok(corpus) => corpus,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    let eval = evaluate(&corpus);
+    print_report(&eval);
+
+    let accuracy = ratio(eval.correct, eval.total);
+    println!();
+    println!("Accuracy: {:.4} ({}/{})", accuracy, eval.correct, eval.total);
+
+    if has_flag(args, "--update-baseline") {
+        let baseline = AccuracyBaseline { accuracy };
+        return match serde_json::to_string_pretty(&baseline)
+            .map_err(|e| e.to_string())
+            .and_then(|json| std::fs::write(baseline_path, json + "\n").map_err(|e| e.to_string()))
+        {
+            Ok(()) => {
+                println!("✓ Baseline updated in {}", baseline_path);
+                0
+            }
+            Err(e) => {
+                eprintln!("Failed to write baseline {}: {}", baseline_path, e);
+                2
+            }
+        };
+    }
+
+    match load_baseline(baseline_path) {
+        Some(baseline) if accuracy + f64::EPSILON < baseline.accuracy => {
+            eprintln!(
+                "✗ Accuracy {:.4} dropped below baseline {:.4}",
+                accuracy, baseline.accuracy
+            );
+            1
+        }
+        Some(baseline) => {
+            println!("✓ Accuracy meets baseline {:.4}", baseline.accuracy);
+            0
+        }
+        None => {
+            eprintln!("WARNING: No baseline found at {}, run with --update-baseline", baseline_path);
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn corpus(value: Value) -> LabeledCorpus {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn shipped_corpus_meets_its_baseline() {
+        let eval = evaluate(&load_corpus(DEFAULT_CORPUS_PATH).unwrap());
+        let baseline = load_baseline(DEFAULT_BASELINE_PATH).unwrap();
+        assert!(ratio(eval.correct, eval.total) + f64::EPSILON >= baseline.accuracy);
+    }
+
+    #[test]
+    fn misses_land_in_the_confusion_matrix() {
+        let eval = evaluate(&corpus(json!({
+            "samples": [
+                { "tags": { "amenity": "pharmacy" }, "expected": "health" },
+                { "tags": { "amenity": "pharmacy" }, "expected": "market" },
+                { "tags": { "building": "yes" } }
+            ]
+        })));
+        assert_eq!((eval.correct, eval.total), (2, 3));
+        assert_eq!(eval.confusion[&("market".to_string(), "health".to_string())], 1);
+        assert_eq!(eval.confusion[&(NO_CATEGORY.to_string(), NO_CATEGORY.to_string())], 1);
+        assert_eq!(count_where(&eval, |e, p| e != p), 1);
+        assert!(eval.labels.contains(&NO_CATEGORY.to_string()));
+    }
+
+    #[test]
+    fn empty_corpus_has_zero_accuracy() {
+        let eval = evaluate(&corpus(json!({ "samples": [] })));
+        assert_eq!(eval.total, 0);
+        assert_eq!(ratio(eval.correct, eval.total), 0.0);
+        assert!(eval.labels.is_empty());
+    }
+}
@@ -0,0 +1,456 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::models::{BatchPoint, BatchRecord, DistanceMode, ScoringOptions, SingleLocationRequest};
+use crate::services::export::{score_header, score_row, UTF8_BOM};
+use crate::services::geojson::points_feature_collection;
+use crate::services::overpass::OverpassService;
+use crate::services::walking::MAX_WALK_MINUTES;
+use crate::{process_location, LOCATION_DELAY_SECS};
+
+use super::{flag_value, has_flag};
+
+const DEFAULT_CACHE_DIR: &str = "cache/overpass";
+const DEFAULT_CACHE_MAX_AGE_DAYS: u64 = 7;
+const CHECKPOINT_SUFFIX: &str = ".progress.jsonl";
+
+const LAT_COLUMNS: &[&str] = &["lat", "latitude", "y"];
+const LNG_COLUMNS: &[&str] = &["lng", "lon", "long", "longitude", "x"];
+const ID_COLUMNS: &[&str] = &["id", "name", "site", "site_id"];
+const COUNTRY_COLUMNS: &[&str] = &["country", "country_code"];
+
+#[derive(Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Csv,
+    GeoJson,
+    Json,
+}
+
+fn parse_format(value: &str) -> Option<OutputFormat> {
+    match value.to_lowercase().as_str() {
+        "csv" => Some(OutputFormat::Csv),
+        "geojson" => Some(OutputFormat::GeoJson),
+        "json" => Some(OutputFormat::Json),
+        _ => None,
+    }
+}
+
+fn format_from_path(path: &str) -> OutputFormat {
+    let lower = path.to_lowercase();
+    if lower.ends_with(".geojson") {
+        OutputFormat::GeoJson
+    } else if lower.ends_with(".json") {
+        OutputFormat::Json
+    } else {
+        OutputFormat::Csv
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Quoting {
+    Unquoted,
+    Quoted,
+    // Just past a closing quote: another quote means it was a doubled `""`.
+    Closed,
+}
+
+// Quotes only open a field at its start; elsewhere in an unquoted field they are text.
+fn parse_csv_line(line: &str, delimiter: char) -> Vec<String> {
+    let (mut fields, current, _) = line.chars().fold(
+        (Vec::new(), String::new(), Quoting::Unquoted),
+        |(mut fields, mut current, quoting), c| match (c, quoting) {
+            ('"', Quoting::Quoted) => (fields, current, Quoting::Closed),
+            ('"', Quoting::Closed) => {
+                current.push('"');
+                (fields, current, Quoting::Quoted)
+            }
+            ('"', Quoting::Unquoted) if current.trim().is_empty() => (fields, String::new(), Quoting::Quoted),
+            (c, Quoting::Quoted) => {
+                current.push(c);
+                (fields, current, Quoting::Quoted)
+            }
+            (c, _) if c == delimiter => {
+                fields.push(current.trim().to_string());
+                (fields, String::new(), Quoting::Unquoted)
+            }
+            (c, _) => {
+                current.push(c);
+                (fields, current, Quoting::Unquoted)
+            }
+        },
+    );
+    fields.push(current.trim().to_string());
+    fields
+}
+
+fn find_column(header: &[String], candidates: &[&str]) -> Option<usize> {
+    header
+        .iter()
+        .position(|h| candidates.contains(&h.to_lowercase().as_str()))
+}
+
+fn valid_coordinates(lat: f64, lng: f64) -> bool {
+    (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lng)
+}
+
+fn read_points_csv(content: &str) -> Result<Vec<BatchPoint>, String> {
+    // Numbered before blank lines are dropped, so fallback ids and warnings name the
+    // line as it appears in the file.
+    let mut lines = content
+        .trim_start_matches(UTF8_BOM)
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|(_, l)| !l.trim().is_empty());
+    let (_, header_line) = lines.next().ok_or("Input CSV is empty")?;
+    let delimiter = [',', ';', '\t']
+        .into_iter()
+        .max_by_key(|d| header_line.matches(*d).count())
+        .unwrap_or(',');
+    let header = parse_csv_line(header_line, delimiter);
+
+    let lat_col = find_column(&header, LAT_COLUMNS).ok_or("Input CSV needs a lat/latitude column")?;
+    let lng_col = find_column(&header, LNG_COLUMNS).ok_or("Input CSV needs a lng/lon/longitude column")?;
+    let id_col = find_column(&header, ID_COLUMNS);
+    let country_col = find_column(&header, COUNTRY_COLUMNS);
+
+    Ok(lines
+        .filter_map(|(line_number, line)| {
+            let fields = parse_csv_line(line, delimiter);
+            let coordinate = |col: usize| fields.get(col).and_then(|v| v.parse::<f64>().ok());
+            match (coordinate(lat_col), coordinate(lng_col)) {
+                (Some(lat), Some(lng)) if valid_coordinates(lat, lng) => Some(BatchPoint {
+                    id: id_col
+                        .and_then(|c| fields.get(c))
+                        .filter(|v| !v.is_empty())
+                        .cloned()
+                        .unwrap_or_else(|| line_number.to_string()),
+                    lat,
+                    lng,
+                    country: country_col
+                        .and_then(|c| fields.get(c))
+                        .filter(|v| !v.is_empty())
+                        .cloned(),
+                }),
+                _ => {
+                    eprintln!("WARNING: Skipping line {} with invalid coordinates", line_number);
+                    None
+                }
+            }
+        })
+        .collect())
+}
+
+fn feature_string(feature: &Value, key: &str) -> Option<String> {
+    let value = feature.get(key).or_else(|| feature["properties"].get(key))?;
+    value
+        .as_str()
+        .map(|s| s.to_string())
+        .or_else(|| value.as_i64().map(|n| n.to_string()))
+}
+
+fn read_points_geojson(content: &str) -> Result<Vec<BatchPoint>, String> {
+    let collection: Value = serde_json::from_str(content).map_err(|e| format!("Invalid GeoJSON: {}", e))?;
+    let features = collection["features"]
+        .as_array()
+        .ok_or("GeoJSON input must be a FeatureCollection")?;
+
+    Ok(features
+        .iter()
+        .enumerate()
+        .filter_map(|(i, feature)| {
+            let coords = &feature["geometry"]["coordinates"];
+            let (lng, lat) = (coords[0].as_f64()?, coords[1].as_f64()?);
+            if feature["geometry"]["type"] != "Point" || !valid_coordinates(lat, lng) {
+                eprintln!("WARNING: Skipping feature {} that is not a valid Point", i + 1);
+                return None;
+            }
+            Some(BatchPoint {
+                id: feature_string(feature, "id")
+                    .or_else(|| feature_string(feature, "name"))
+                    .unwrap_or_else(|| (i + 1).to_string()),
+                lat,
+                lng,
+                country: feature_string(feature, "country"),
+            })
+        })
+        .collect())
+}
+
+fn load_points(path: &str) -> Result<Vec<BatchPoint>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let lower = path.to_lowercase();
+    if lower.ends_with(".geojson") || lower.ends_with(".json") {
+        read_points_geojson(&content)
+    } else {
+        read_points_csv(&content)
+    }
+}
+
+// Checkpoints and output rows are matched to input points by id, so ids must be unique.
+fn duplicate_ids(points: &[BatchPoint]) -> Vec<&str> {
+    let mut seen = HashSet::new();
+    let duplicates: BTreeSet<&str> = points
+        .iter()
+        .map(|p| p.id.as_str())
+        .filter(|id| !seen.insert(*id))
+        .collect();
+    duplicates.into_iter().collect()
+}
+
+fn load_checkpoint(path: &str) -> Vec<BatchRecord> {
+    std::fs::read_to_string(path)
+        .map(|content| {
+            content
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn append_checkpoint(path: &str, record: &BatchRecord) -> std::io::Result<()> {
+    let line = serde_json::to_string(record).map_err(std::io::Error::other)?;
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)?;
+    file.sync_data()
+}
+
+fn write_output(path: &str, format: OutputFormat, records: &[BatchRecord]) -> std::io::Result<()> {
+    let content = match format {
+        OutputFormat::Csv => {
+            let rows: String = records
+                .iter()
+                .map(|r| score_row(&r.id, &r.location, ','))
+                .collect();
+            format!("{}{}{}", UTF8_BOM, score_header(','), rows)
+        }
+        OutputFormat::GeoJson => {
+            let pairs: Vec<(String, _)> = records
+                .iter()
+                .map(|r| (r.id.clone(), r.location.clone()))
+                .collect();
+            serde_json::to_string_pretty(&points_feature_collection(&pairs)).map_err(std::io::Error::other)?
+        }
+        OutputFormat::Json => serde_json::to_string_pretty(records).map_err(std::io::Error::other)?,
+    };
+    std::fs::write(path, content)
+}
+
+pub async fn run(args: &[String]) -> i32 {
+    let (input, output) = match (flag_value(args, "--input"), flag_value(args, "--output")) {
+        (Some(input), Some(output)) => (input, output),
+        _ => {
+            eprintln!(
+                "Usage: backend batch --input <points.csv|points.geojson> --output <results.csv|.geojson|.json> \
+                 [--format csv|geojson|json] [--distance straight|walking] [--walk-minutes <n>] [--essentials] [--resume] [--cache-dir <dir>] [--cache-max-age-days <n>]"
+            );
+            return 2;
+        }
+    };
+
+    let format = match flag_value(args, "--format") {
+        Some(value) => match parse_format(value) {
+            Some(format) => format,
+            None => {
+                eprintln!("Unsupported format '{}'. Use csv, geojson or json", value);
+                return 2;
+            }
+        },
+        None => format_from_path(output),
+    };
+
+    let distance_mode = match flag_value(args, "--distance").map(|v| v.to_lowercase()).as_deref() {
+        None | Some("straight") => DistanceMode::Straight,
+        Some("walking") => DistanceMode::Walking,
+        Some(other) => {
+            eprintln!("Unsupported distance mode '{}'. Use straight or walking", other);
+            return 2;
+        }
+    };
+    let walk_minutes = match flag_value(args, "--walk-minutes").map(|v| v.parse::<f64>()) {
+        None => None,
+        Some(Ok(minutes)) if minutes > 0.0 && minutes <= MAX_WALK_MINUTES => Some(minutes),
+        Some(_) => {
+            eprintln!("--walk-minutes must be a number between 0 and {}", MAX_WALK_MINUTES);
+            return 2;
+        }
+    };
+    // Resumed runs reuse the disk cache, so old OSM responses are refetched after a while;
+    // 0 refetches everything.
+    let cache_max_age_days = match flag_value(args, "--cache-max-age-days").map(|v| v.parse::<u64>()) {
+        None => DEFAULT_CACHE_MAX_AGE_DAYS,
+        Some(Ok(days)) => days,
+        Some(Err(_)) => {
+            eprintln!("--cache-max-age-days must be a whole number of days");
+            return 2;
+        }
+    };
+    let options = ScoringOptions {
+        distance_mode,
+        walk_minutes,
+        essentials: has_flag(args, "--essentials"),
+        ..ScoringOptions::default()
+    };
+
+    let points = match load_points(input) {
+        Ok(points) => points,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    let duplicates = duplicate_ids(&points);
+    if !duplicates.is_empty() {
+        eprintln!(
+            "Point ids must be unique, found duplicates: {}. Add an id column or rename the rows",
+            duplicates.join(", ")
+        );
+        return 2;
+    }
+
+    let checkpoint_path = format!("{}{}", output, CHECKPOINT_SUFFIX);
+    let previous = if has_flag(args, "--resume") {
+        load_checkpoint(&checkpoint_path)
+    } else {
+        if let Err(e) = std::fs::write(&checkpoint_path, "") {
+            eprintln!("Failed to create checkpoint {}: {}", checkpoint_path, e);
+            return 2;
+        }
+        Vec::new()
+    };
+
+    let done: HashSet<String> = previous.iter().map(|r| r.id.clone()).collect();
+    let pending: Vec<&BatchPoint> = points.iter().filter(|p| !done.contains(&p.id)).collect();
+    println!(
+        "Batch: {} points, {} already done, {} pending",
+        points.len(),
+        points.len() - pending.len(),
+        pending.len()
+    );
+
+    let cache_dir = PathBuf::from(flag_value(args, "--cache-dir").unwrap_or(DEFAULT_CACHE_DIR));
+    let service = Arc::new(OverpassService::new()
+        .with_cache_dir(cache_dir)
+        .with_cache_max_age(Duration::from_secs(cache_max_age_days * 24 * 60 * 60))
+        .with_memory_cache());
+    let total = pending.len();
+
+    let mut failures = 0usize;
+    for (i, point) in pending.iter().enumerate() {
+        let loc = SingleLocationRequest {
+            lat: Some(point.lat),
+            lng: Some(point.lng),
+            country: point.country.clone(),
+            polygon: None,
+            relation: None,
+        };
+
+        let requests_before = service.network_requests();
+        match process_location(&service, &options, &loc, i, total).await {
+            Ok(location) => {
+                let record = BatchRecord { id: point.id.clone(), location };
+                if let Err(e) = append_checkpoint(&checkpoint_path, &record) {
+                    eprintln!("Failed to write checkpoint {}: {}", checkpoint_path, e);
+                    return 2;
+                }
+            }
+            Err((_, message)) => {
+                failures += 1;
+                eprintln!("✗ Point '{}' failed: {}", point.id, message);
+            }
+        }
+
+        // Points answered entirely from the cache never reached Overpass, so they need no pause.
+        if i + 1 < total && service.network_requests() > requests_before {
+            tokio::time::sleep(Duration::from_secs(LOCATION_DELAY_SECS)).await;
+        }
+    }
+
+    let completed: HashMap<String, BatchRecord> = load_checkpoint(&checkpoint_path)
+        .into_iter()
+        .map(|r| (r.id.clone(), r))
+        .collect();
+    let ordered: Vec<BatchRecord> = points
+        .iter()
+        .filter_map(|p| completed.get(&p.id).cloned())
+        .collect();
+
+    if let Err(e) = write_output(output, format, &ordered) {
+        eprintln!("Failed to write {}: {}", output, e);
+        return 2;
+    }
+
+    println!("✓ Wrote {} of {} points to {}", ordered.len(), points.len(), output);
+    if failures > 0 {
+        eprintln!("{} points failed; rerun with --resume to retry them", failures);
+        1
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(points: &[BatchPoint]) -> Vec<&str> {
+        points.iter().map(|p| p.id.as_str()).collect()
+    }
+
+    #[test]
+    fn csv_fields_handle_quotes_and_delimiters() {
+        assert_eq!(parse_csv_line(r#"a, "b,c" ,"say ""hi""""#, ','), vec!["a", "b,c", r#"say "hi""#]);
+        assert_eq!(parse_csv_line("", ';'), vec![""]);
+        assert_eq!(parse_csv_line("x;;y", ';'), vec!["x", "", "y"]);
+    }
+
+    #[test]
+    fn quotes_inside_unquoted_fields_are_text() {
+        assert_eq!(parse_csv_line(r#"Toko 5" Jaya,-6.2,106.8"#, ','), vec![r#"Toko 5" Jaya"#, "-6.2", "106.8"]);
+        assert_eq!(parse_csv_line(r#"a"b,c"#, ','), vec![r#"a"b"#, "c"]);
+    }
+
+    #[test]
+    fn csv_points_detect_delimiter_and_columns() {
+        let content = "\u{feff}Name;Latitude;Longitude\nPasar;-6.2;106.8\n\n;-6.3;106.9\nbad;100;0\n";
+        let points = read_points_csv(content).unwrap();
+        assert_eq!(ids(&points), vec!["Pasar", "4"]);
+        assert_eq!((points[0].lat, points[0].lng), (-6.2, 106.8));
+    }
+
+    #[test]
+    fn csv_without_coordinate_columns_is_rejected() {
+        assert!(read_points_csv("id,x_coord\n1,2").is_err());
+        assert!(read_points_csv("").is_err());
+    }
+
+    #[test]
+    fn duplicate_ids_are_reported_once() {
+        let points = read_points_csv("name,lat,lng\nA,0,0\nB,1,1\nA,2,2\nB,3,3\nA,4,4\n").unwrap();
+        assert_eq!(duplicate_ids(&points), vec!["A", "B"]);
+        assert!(duplicate_ids(&[]).is_empty());
+    }
+
+    #[test]
+    fn row_number_ids_can_collide_with_explicit_ids() {
+        let points = read_points_csv("id,lat,lng\n3,0,0\n,1,1\n").unwrap();
+        assert_eq!(duplicate_ids(&points), vec!["3"]);
+    }
+
+    #[test]
+    fn geojson_points_use_id_then_name_and_skip_other_geometries() {
+        let content = r#"{"type":"FeatureCollection","features":[
+            {"type":"Feature","id":7,"geometry":{"type":"Point","coordinates":[106.8,-6.2]},"properties":{}},
+            {"type":"Feature","geometry":{"type":"Point","coordinates":[106.9,-6.3]},"properties":{"name":"Taman"}},
+            {"type":"Feature","geometry":{"type":"LineString","coordinates":[[0,0],[1,1]]},"properties":{}}
+        ]}"#;
+        assert_eq!(ids(&read_points_geojson(content).unwrap()), vec!["7", "Taman"]);
+    }
+}
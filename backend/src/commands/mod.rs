@@ -0,0 +1,33 @@
+pub mod accuracy;
+pub mod batch;
+pub mod queries;
+pub mod report;
+pub mod reproduce;
+
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(|s| s.as_str())
+}
+
+fn has_flag(args: &[String], flag: &str) -> bool {
+    args.iter().any(|a| a == flag)
+}
+
+pub async fn run(args: &[String]) -> Option<i32> {
+    let (command, rest) = args.split_first()?;
+
+    match command.as_str() {
+        "serve" => None,
+        "accuracy" => Some(accuracy::run(rest)),
+        "batch" => Some(batch::run(rest).await),
+        "queries" => Some(queries::run(rest)),
+        "report" => Some(report::run(rest)),
+        "reproduce" => Some(reproduce::run(rest).await),
+        other => {
+            eprintln!("Unknown command '{}'. Available: serve, accuracy, batch, queries, report, reproduce", other);
+            Some(2)
+        }
+    }
+}
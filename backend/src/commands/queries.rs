@@ -0,0 +1,28 @@
+use crate::services::language_packs::resolve_locale;
+use crate::services::query_builder::generate_overpass_query;
+use crate::{CATEGORIES, SEARCH_RADIUS};
+
+use super::flag_value;
+
+pub fn run(args: &[String]) -> i32 {
+    let coordinate = |flag: &str| flag_value(args, flag).and_then(|v| v.parse::<f64>().ok());
+
+    let (lat, lng) = match (coordinate("--lat"), coordinate("--lng")) {
+        (Some(lat), Some(lng)) => (lat, lng),
+        _ => {
+            eprintln!("Usage: backend queries --lat <lat> --lng <lng> [--country <code>]");
+            return 2;
+        }
+    };
+
+    let locale = resolve_locale(lat, lng, flag_value(args, "--country"));
+    println!("# language packs: {}", locale.packs.join(", "));
+
+    CATEGORIES.iter().for_each(|category| {
+        println!();
+        println!("# {}", category);
+        println!("{}", generate_overpass_query(&locale.patterns, category, lat, lng, SEARCH_RADIUS));
+    });
+
+    0
+}
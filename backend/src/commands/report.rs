@@ -0,0 +1,47 @@
+use crate::models::LocationData;
+use crate::services::report::{render_html, render_pdf};
+
+use super::flag_value;
+
+fn load_results(path: &str) -> Result<Vec<LocationData>, String> {
+    std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))
+        .and_then(|content| {
+            serde_json::from_str(&content).map_err(|e| format!("Invalid results file {}: {}", path, e))
+        })
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let (input, output) = match (flag_value(args, "--input"), flag_value(args, "--output")) {
+        (Some(input), Some(output)) => (input, output),
+        _ => {
+            eprintln!("Usage: backend report --input <results.json> --output <report.html|report.pdf>");
+            return 2;
+        }
+    };
+
+    let results = match load_results(input) {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    let bytes = if output.to_lowercase().ends_with(".pdf") {
+        render_pdf(&results)
+    } else {
+        render_html(&results).into_bytes()
+    };
+
+    match std::fs::write(output, bytes) {
+        Ok(()) => {
+            println!("✓ Wrote report for {} locations to {}", results.len(), output);
+            0
+        }
+        Err(e) => {
+            eprintln!("Failed to write {}: {}", output, e);
+            2
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::models::{CalculateScoreRequest, LocationData};
+use crate::services::overpass::OverpassService;
+use crate::{process_location, validate_locations};
+
+use super::flag_value;
+
+const DEFAULT_CACHE_DIR: &str = "cache/overpass";
+const DEFAULT_RUNS: usize = 3;
+
+fn load_request(path: &str) -> Result<CalculateScoreRequest, String> {
+    std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))
+        .and_then(|content| {
+            serde_json::from_str(&content).map_err(|e| format!("Invalid request {}: {}", path, e))
+        })
+}
+
+async fn score_once(service: &Arc<OverpassService>, request: &CalculateScoreRequest) -> Result<Vec<String>, String> {
+    let total = request.locations.len();
+    let mut serialized = Vec::with_capacity(total);
+    for (i, loc) in request.locations.iter().enumerate() {
+        let location: LocationData = process_location(service, &request.options, loc, i, total)
+            .await
+            .map_err(|(_, message)| message)?;
+        serialized.push(serde_json::to_string(&location).map_err(|e| e.to_string())?);
+    }
+    Ok(serialized)
+}
+
+// Scores the same request several times against the Overpass cache and compares the
+// serialized results byte for byte. The first run fills the cache, so later runs see
+// identical inputs and any difference comes from the pipeline itself.
+pub async fn run(args: &[String]) -> i32 {
+    let input = match flag_value(args, "--input") {
+        Some(input) => input,
+        None => {
+            eprintln!("Usage: backend reproduce --input <request.json> [--runs <n>] [--cache-dir <dir>]");
+            return 2;
+        }
+    };
+
+    let runs = match flag_value(args, "--runs").map(|v| v.parse::<usize>()) {
+        None => DEFAULT_RUNS,
+        Some(Ok(runs)) if runs >= 2 => runs,
+        Some(_) => {
+            eprintln!("--runs must be a whole number of at least 2");
+            return 2;
+        }
+    };
+
+    let request = match load_request(input) {
+        Ok(request) => request,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+    if let Err((_, message)) = validate_locations(&request) {
+        eprintln!("{}", message);
+        return 2;
+    }
+
+    let cache_dir = PathBuf::from(flag_value(args, "--cache-dir").unwrap_or(DEFAULT_CACHE_DIR));
+    let service = Arc::new(OverpassService::new().with_cache_dir(cache_dir));
+
+    let mut results = Vec::with_capacity(runs);
+    for run in 0..runs {
+        println!("Run {} of {}...", run + 1, runs);
+        match score_once(&service, &request).await {
+            Ok(serialized) => results.push(serialized),
+            Err(message) => {
+                eprintln!("✗ Run {} failed: {}", run + 1, message);
+                return 2;
+            }
+        }
+    }
+
+    let reference = &results[0];
+    let mismatches: Vec<(usize, usize)> = results
+        .iter()
+        .enumerate()
+        .skip(1)
+        .flat_map(|(run, serialized)| {
+            serialized
+                .iter()
+                .zip(reference)
+                .enumerate()
+                .filter(|(_, (a, b))| a != b)
+                .map(move |(location, _)| (run, location))
+        })
+        .collect();
+
+    if mismatches.is_empty() {
+        println!("✓ {} runs produced byte-identical results for {} locations", runs, reference.len());
+        0
+    } else {
+        mismatches.iter().for_each(|(run, location)| {
+            eprintln!("✗ Run {} differs from run 1 for location {}", run + 1, location + 1);
+        });
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::models::OverpassElement;
+    use crate::services::language_packs::resolve_locale;
+    use crate::services::nearby::cmp_by_distance;
+    use crate::services::score_calculator::{calculate_scores, classify_fetched, measure_facilities};
+
+    const LAT: f64 = 52.52;
+    const LNG: f64 = 13.405;
+
+    fn node(id: u64, lat: f64, lng: f64, tags: serde_json::Value) -> OverpassElement {
+        serde_json::from_value(json!({ "type": "node", "id": id, "lat": lat, "lon": lng, "tags": tags })).unwrap()
+    }
+
+    fn fetched() -> Vec<(String, Vec<OverpassElement>)> {
+        let pharmacy = node(1, LAT + 0.001, LNG, json!({ "amenity": "pharmacy", "name": "Apotheke" }));
+        vec![
+            (
+                "health".to_string(),
+                vec![
+                    pharmacy.clone(),
+                    // Same distance as the pharmacy, so only the id orders them.
+                    node(2, LAT - 0.001, LNG, json!({ "amenity": "clinic", "name": "Praxis" })),
+                ],
+            ),
+            (
+                "market".to_string(),
+                vec![
+                    node(3, LAT, LNG + 0.002, json!({ "shop": "supermarket", "name": "Markt" })),
+                    // Fetched twice under different categories.
+                    pharmacy,
+                ],
+            ),
+            (
+                "transport".to_string(),
+                vec![node(4, LAT, LNG - 0.002, json!({ "highway": "bus_stop", "name": "Halt" }))],
+            ),
+        ]
+    }
+
+    fn score(fetched: &[(String, Vec<OverpassElement>)]) -> String {
+        let patterns = resolve_locale(LAT, LNG, None).patterns;
+        let classified = classify_fetched(fetched, &patterns);
+        let mut facilities = measure_facilities(&classified, LAT, LNG, 500.0);
+        facilities.sort_by(cmp_by_distance);
+        let (scores, counts) = calculate_scores(&facilities);
+        serde_json::to_string(&(facilities, scores, counts)).unwrap()
+    }
+
+    #[test]
+    fn scoring_is_byte_identical_across_runs_and_input_orders() {
+        let input = fetched();
+        let shuffled: Vec<(String, Vec<OverpassElement>)> = input
+            .iter()
+            .rev()
+            .map(|(category, elements)| (category.clone(), elements.iter().rev().cloned().collect()))
+            .collect();
+
+        let first = score(&input);
+        assert_eq!(first.matches("\"id\":\"node/1\"").count(), 1);
+        assert_eq!(first, score(&input));
+        assert_eq!(first, score(&shuffled));
+    }
+}
@@ -1,40 +1,79 @@
+mod commands;
 mod models;
+mod routes;
 mod services;
 
 use axum::{
+    extract::Query,
     routing::{get, post},
     Router,
     Json,
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
 };
 use std::net::SocketAddr;
 use tower_http::cors::CorsLayer;
 use std::collections::HashSet;
-use std::sync::Arc;
-use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
 use once_cell::sync::Lazy;
 
-use crate::models::{CalculateScoreRequest, LocationData};
+
+use crate::models::{
+    AreaSummary, CalculateScoreRequest, Catchment, DistanceMode, Facility, GreenSpace, Isochrone, LocationData, NightSafety, NuisancePenalty, RoadSafety,
+    OutputParams, OverpassElement, ScoringOptions, SingleLocationRequest,
+};
+use crate::routes::export::{export_facilities_csv, export_scores_csv};
+use crate::routes::heatmap::generate_heatmap;
+use crate::routes::report::generate_report;
+use crate::services::essentials::{essentials_report, essentials_threshold};
+use crate::services::geojson::locations_feature_collection;
+use crate::services::green_space::green_space_summary;
+use crate::services::night_safety::night_safety_summary;
+use crate::services::road_safety::road_safety_summary;
+use crate::services::nuisance::{nuisance_penalties, nuisance_query};
+use crate::services::nearby::{cmp_by_distance, get_top_n, nearest_by_category, top_by_category, MAX_TOP_N};
 use crate::services::overpass::OverpassService;
+use crate::services::area_scoring::{area_summary, facility_distance, measure_area_facilities, relation_outer_ring};
+use crate::services::geo::{bounds_span, pad_bounds, polygon_outer_ring, ring_bounds, ring_centroid};
+use crate::services::language_packs::{resolve_locale, Locale};
+use crate::services::query_builder::{
+    generate_overpass_bbox_query, generate_overpass_query, green_space_query, relation_boundary_query,
+    street_query, walking_network_query,
+};
 
-use crate::services::score_calculator::{process_facilities, calculate_scores};
-use futures::stream::{self, StreamExt};
+use crate::services::score_calculator::{
+    calculate_adjusted_scores, classify_fetched, count_subcategories, measure_facilities, measure_facility,
+    CONTEXT_LAYERS,
+};
+use crate::services::shared_fetch::{plan_shared_areas, SharedArea};
+use crate::services::spatial_index::{AreaCache, FetchedArea};
+use crate::services::walking::{
+    catchment_distance, isochrone_budgets, minutes_to_meters, uses_walking_distance, walking_speed,
+    WalkingDistances, WalkingGraph, MAX_WALK_MINUTES, WALKING_SPEED_RANGE_KMH,
+};
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
 
-pub static QUERY_CONFIG: Lazy<Value> = Lazy::new(|| {
-    std::fs::read_to_string("config/queries.json")
-        .ok()
-        .and_then(|content| serde_json::from_str(&content).ok())
-        .unwrap_or(json!({}))
-});
+pub const CATEGORIES: [&str; 10] = [
+    "health", "education", "market", "transport", "walkability",
+    "recreation", "safety", "police", "religious", "accessibility"
+];
 
 const MAX_FACILITY_DISTANCE: f64 = 500.0;
-const SEARCH_RADIUS: i32 = 500;
+pub const SEARCH_RADIUS: i32 = 500;
 const MAX_NEARBY_FACILITIES: usize = 10;
 const MAX_RETRIES: u32 = 3;
+pub const GEOJSON_CONTENT_TYPE: &str = "application/geo+json";
 const INITIAL_DELAY: u64 = 5;
+pub const LOCATION_DELAY_SECS: u64 = 3;
+// Largest side of any bbox fetched in one go, for area targets and heatmaps alike.
+pub const MAX_AREA_SPAN_M: f64 = 10_000.0;
+
+pub static OVERPASS_SERVICE: Lazy<Arc<OverpassService>> = Lazy::new(|| Arc::new(OverpassService::new()));
 
+// The first occurrence of an id wins; fetch results arrive in query order, so the
+// surviving copy is the same on every run.
 struct DeduplicationState {
-    facilities: Vec<crate::models::Facility>,
+    facilities: Vec<Facility>,
     seen_ids: HashSet<String>,
 }
 
@@ -46,23 +85,32 @@ impl DeduplicationState {
         }
     }
 
-    fn add_facility(mut self, facility: crate::models::Facility) -> Self {
+    fn add_facility(mut self, facility: Facility) -> Self {
         if self.seen_ids.insert(facility.id.clone()) {
             self.facilities.push(facility);
         }
         self
     }
 
-    fn into_unique_facilities(self) -> Vec<crate::models::Facility> {
+    fn into_unique_facilities(self) -> Vec<Facility> {
         self.facilities
     }
 }
 
 #[tokio::main]
 async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = commands::run(&args).await {
+        std::process::exit(code);
+    }
+
     let app = Router::new()
         .route("/", get(root))
         .route("/calculate-score", post(calculate_score))
+        .route("/export/scores.csv", post(export_scores_csv))
+        .route("/export/facilities.csv", post(export_facilities_csv))
+        .route("/report", post(generate_report))
+        .route("/heatmap", post(generate_heatmap))
         .layer(CorsLayer::permissive());
 
     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
@@ -85,76 +133,452 @@ async fn root() -> &'static str {
     "Futuricty Backend is running!"
 }
 
-async fn process_location(
+pub async fn fetch_with_retry(
+    service: &Arc<OverpassService>,
+    queries: Vec<(String, String)>,
+) -> Result<Vec<(String, Vec<OverpassElement>)>, Box<dyn std::error::Error + Send + Sync>> {
+    let queries_arc = Arc::new(queries);
+    let initial = (0..MAX_RETRIES).map(|attempt| {
+        let q = queries_arc.clone();
+        let s = service.clone();
+        async move {
+            if attempt > 0 {
+                let delay_secs = INITIAL_DELAY * 2u64.pow(attempt - 1);
+                println!("Location retry {} after {} seconds...", attempt, delay_secs);
+                tokio::time::sleep(std::time::Duration::from_secs(delay_secs)).await;
+            }
+            
+            s.fetch_facilities((*q).clone()).await
+        }
+    }).collect::<Vec<_>>();
+    
+    let result = stream::iter(initial)
+        .fold(None, |acc, fut| async move {
+            if let Some(Ok(_)) = acc {
+                acc
+            } else {
+                Some(fut.await)
+            }
+        })
+        .await;
+
+    match result {
+        Some(Ok(res)) => Ok(res),
+        Some(Err(e)) => Err(e),
+        None => Err(Box::<dyn std::error::Error + Send + Sync>::from("No attempts executed")),
+    }
+}
+
+pub async fn process_location(
     service: &Arc<OverpassService>,
-    loc: &crate::models::SingleLocationRequest,
+    options: &ScoringOptions,
+    loc: &SingleLocationRequest,
     index: usize,
     total: usize,
 ) -> Result<LocationData, (StatusCode, String)> {
-    println!("Processing location {} of {}...", index + 1, total);
-    
-    let categories = vec![
-        "health", "education", "market", "transport", "walkability",
-        "recreation", "safety", "police", "religious", "accessibility"
-    ];
+    let areas = Mutex::new(AreaCache::new(MAX_FACILITY_DISTANCE));
+    process_location_in(service, &areas, options, loc, index, total).await
+}
 
-    let queries: Vec<(String, String)> = categories.iter().map(|&cat| {
-        let query = generate_overpass_query(cat, loc.lat, loc.lng);
-        (cat.to_string(), query)
-    }).collect();
+struct ScoringTarget {
+    lat: f64,
+    lng: f64,
+    address: String,
+    ring: Option<Vec<(f64, f64)>>,
+}
 
-    let attempt_results = {
-        let queries_arc = Arc::new(queries);
-        let initial = (0..MAX_RETRIES).map(|attempt| {
-            let q = queries_arc.clone();
-            let s = service.clone();
-            async move {
-                if attempt > 0 {
-                    let delay_secs = INITIAL_DELAY * 2u64.pow(attempt - 1);
-                    println!("Location retry {} after {} seconds...", attempt, delay_secs);
-                    tokio::time::sleep(std::time::Duration::from_secs(delay_secs)).await;
-                }
-                
-                s.fetch_facilities((*q).clone()).await
-            }
-        }).collect::<Vec<_>>();
-        
-        let result = stream::iter(initial)
-            .fold(None, |acc, fut| async move {
-                if let Some(Ok(_)) = acc {
-                    acc
-                } else {
-                    Some(fut.await)
-                }
-            })
-            .await;
+async fn fetch_relation_boundary(
+    service: &Arc<OverpassService>,
+    relation_id: u64,
+    index: usize,
+) -> Result<(Vec<(f64, f64)>, String), (StatusCode, String)> {
+    let query = relation_boundary_query(relation_id);
+    let data = fetch_with_retry(service, vec![("boundary".to_string(), query)])
+        .await
+        .map_err(|e| {
+            (StatusCode::SERVICE_UNAVAILABLE,
+                format!("Failed to fetch relation {} for location {}: {}", relation_id, index + 1, e))
+        })?;
+
+    let relation = data
+        .iter()
+        .flat_map(|(_, elements)| elements)
+        .find(|e| e.element_type == "relation" && e.id == relation_id);
 
-        match result {
-            Some(Ok(res)) => Ok(res),
-            Some(Err(e)) => Err(e),
-            None => Err(Box::<dyn std::error::Error + Send + Sync>::from("No attempts executed")),
+    let ring = relation.and_then(relation_outer_ring).ok_or((
+        StatusCode::UNPROCESSABLE_ENTITY,
+        format!("Relation {} has no closed outer boundary", relation_id),
+    ))?;
+
+    let label = relation
+        .and_then(|r| r.tags.as_ref())
+        .and_then(|tags| tags.get("name"))
+        .map(|name| format!("{} (relation {})", name, relation_id))
+        .unwrap_or_else(|| format!("OSM relation {}", relation_id));
+
+    Ok((ring, label))
+}
+
+async fn resolve_target(
+    service: &Arc<OverpassService>,
+    loc: &SingleLocationRequest,
+    index: usize,
+) -> Result<ScoringTarget, (StatusCode, String)> {
+    if let Some((lat, lng)) = loc.point() {
+        return Ok(ScoringTarget { lat, lng, address: format!("{}, {}", lat, lng), ring: None });
+    }
+
+    let (ring, label) = match (&loc.polygon, loc.relation) {
+        (Some(geometry), _) => {
+            let ring = polygon_outer_ring(geometry).ok_or((StatusCode::BAD_REQUEST,
+                format!("Location {} polygon must be a GeoJSON Polygon geometry", index + 1)))?;
+            (ring, None)
+        }
+        (None, Some(relation_id)) => {
+            let (ring, label) = fetch_relation_boundary(service, relation_id, index).await?;
+            (ring, Some(label))
+        }
+        (None, None) => {
+            return Err((StatusCode::BAD_REQUEST,
+                format!("Location {} needs lat/lng, a polygon or a relation id", index + 1)))
         }
     };
 
-    let facilities_data = attempt_results
+    let (lat, lng) = ring_centroid(&ring);
+    let (height, width) = bounds_span(ring_bounds(&ring));
+    if height > MAX_AREA_SPAN_M || width > MAX_AREA_SPAN_M {
+        return Err((StatusCode::BAD_REQUEST,
+            format!("Location {} area may span at most {} m per side", index + 1, MAX_AREA_SPAN_M)));
+    }
+
+    Ok(ScoringTarget {
+        lat,
+        lng,
+        address: label.unwrap_or_else(|| format!("Area around {:.6}, {:.6}", lat, lng)),
+        ring: Some(ring),
+    })
+}
+
+// Everything classified within `radius` of a point, with straight-line distances but not
+// yet scored. The radius reaches past the scoring catchment when the essentials check is on.
+async fn point_candidates(
+    service: &Arc<OverpassService>,
+    areas: &Mutex<AreaCache>,
+    locale: &Locale,
+    lat: f64,
+    lng: f64,
+    radius: f64,
+    index: usize,
+) -> Result<Vec<Facility>, (StatusCode, String)> {
+    let located = |facility: &Facility| Facility {
+        distance: facility_distance(facility, lat, lng),
+        ..facility.clone()
+    };
+
+    let reused = areas.lock().ok().and_then(|cache| {
+        cache
+            .covering(&locale.packs, lat, lng, radius)
+            .map(|fetched| fetched.within(lat, lng, radius).into_iter().map(located).collect::<Vec<_>>())
+    });
+
+    if let Some(candidates) = reused {
+        println!("✓ Reused previously fetched area for location {}", index + 1);
+        return Ok(candidates);
+    }
+
+    let queries: Vec<(String, String)> = CATEGORIES.iter().map(|&cat| {
+        let query = generate_overpass_query(&locale.patterns, cat, lat, lng, radius.ceil() as i32);
+        (cat.to_string(), query)
+    }).collect();
+
+    let facilities_data = fetch_with_retry(service, queries)
+        .await
         .map_err(|e| {
             (StatusCode::SERVICE_UNAVAILABLE, 
                 format!("Failed to fetch data for location {}: {}", index + 1, e))
         })?;
 
-    // Process facilities - pure functional
-    let all_facilities = facilities_data
+    let classified: Vec<Facility> = classify_fetched(&facilities_data, &locale.patterns);
+
+    let candidates = classified
+        .iter()
+        .map(located)
+        .filter(|f| f.distance <= radius)
+        .collect();
+
+    if let Ok(mut cache) = areas.lock() {
+        let area = FetchedArea::Circle { lat, lng, radius: radius.ceil() };
+        cache.insert(&locale.packs, area, classified);
+    }
+
+    Ok(candidates)
+}
+
+// The essentials check widens every point lookup past the scoring catchment, so it is
+// opt-in. Shared areas are planned with the same reach so they still cover their members.
+fn point_fetch_radius(options: &ScoringOptions) -> f64 {
+    let catchment = catchment_distance(options, MAX_FACILITY_DISTANCE);
+    if options.essentials {
+        catchment.max(essentials_threshold(walking_speed(options)))
+    } else {
+        catchment
+    }
+}
+
+fn score_candidates(candidates: &[Facility], lat: f64, lng: f64, catchment: f64) -> Vec<Facility> {
+    measure_facilities(candidates, lat, lng, catchment)
+        .into_iter()
+        .filter(|f| f.distance <= catchment)
+        .fold(DeduplicationState::new(), |state, facility| state.add_facility(facility))
+        .into_unique_facilities()
+}
+
+// Straight-line candidates are re-measured along the pedestrian network; walking is
+// never shorter, so the candidate set already contains everything reachable.
+async fn walking_analysis(
+    service: &Arc<OverpassService>,
+    options: &ScoringOptions,
+    lat: f64,
+    lng: f64,
+    facilities: Vec<Facility>,
+    catchment: f64,
+    index: usize,
+) -> Result<(Vec<Facility>, Option<Vec<Isochrone>>), (StatusCode, String)> {
+    let speed = walking_speed(options);
+    let budgets = isochrone_budgets(options);
+    let limit = budgets
+        .iter()
+        .map(|&minutes| minutes_to_meters(minutes, speed))
+        .fold(catchment, f64::max);
+
+    let query = walking_network_query(lat, lng, limit.ceil() as i32);
+    let ways: Vec<OverpassElement> = fetch_with_retry(service, vec![("walking".to_string(), query)])
+        .await
+        .map_err(|e| {
+            (StatusCode::SERVICE_UNAVAILABLE,
+                format!("Failed to fetch walking network for location {}: {}", index + 1, e))
+        })?
         .into_iter()
-        .flat_map(|(_category, elements)| {
-            process_facilities(&elements, loc.lat, loc.lng)
+        .flat_map(|(_, elements)| elements)
+        .collect();
+
+    let graph = WalkingGraph::from_ways(&ways);
+    let walking = match WalkingDistances::from_origin(&graph, lat, lng, limit) {
+        Some(walking) => walking,
+        None => {
+            eprintln!("WARNING: Location {} is not near the walking network, keeping straight-line distances", index + 1);
+            return Ok((facilities, None));
+        }
+    };
+
+    let isochrones = (!budgets.is_empty()).then(|| {
+        budgets
+            .iter()
+            .filter_map(|&minutes| {
+                let distance_m = minutes_to_meters(minutes, speed);
+                walking
+                    .isochrone(lat, lng, distance_m)
+                    .map(|polygon| Isochrone { minutes, distance_m, polygon })
+            })
+            .collect()
+    });
+
+    if !uses_walking_distance(options) {
+        return Ok((facilities, isochrones));
+    }
+
+    let reachable: Vec<Facility> = facilities
+        .iter()
+        .filter_map(|facility| {
+            // Zero means the origin lies inside the facility's outline.
+            let distance = if facility.distance == 0.0 {
+                Some(0.0)
+            } else {
+                walking.distance_to_facility(facility)
+            };
+            let distance = distance.filter(|d| *d <= catchment)?;
+            measure_facility(facility, distance, catchment)
         })
-        .filter(|f| f.distance <= MAX_FACILITY_DISTANCE)
+        .collect();
+
+    println!("✓ {} of {} facilities reachable on foot for location {}", reachable.len(), facilities.len(), index + 1);
+    Ok((reachable, isochrones))
+}
+
+// Context layers only adjust the scores, so a failed fetch drops that layer with a
+// warning instead of failing the whole location.
+async fn fetch_context(
+    service: &Arc<OverpassService>,
+    layer: &str,
+    query: String,
+    index: usize,
+) -> Option<Vec<OverpassElement>> {
+    match fetch_with_retry(service, vec![(layer.to_string(), query)]).await {
+        Ok(fetched) => Some(fetched.into_iter().flat_map(|(_, elements)| elements).collect()),
+        Err(e) => {
+            eprintln!("WARNING: Skipping {} for location {}: {}", layer, index + 1, e);
+            None
+        }
+    }
+}
+
+async fn fetch_green_space(
+    service: &Arc<OverpassService>,
+    lat: f64,
+    lng: f64,
+    radius: f64,
+    index: usize,
+) -> Option<GreenSpace> {
+    let query = green_space_query(lat, lng, radius.ceil() as i32);
+    fetch_context(service, "green_space", query, index)
+        .await
+        .map(|elements| green_space_summary(&elements, lat, lng, radius))
+}
+
+// One street fetch feeds both the night-safety and road-safety analyses.
+async fn fetch_street_safety(
+    service: &Arc<OverpassService>,
+    lat: f64,
+    lng: f64,
+    radius: f64,
+    index: usize,
+) -> Option<(NightSafety, RoadSafety)> {
+    let query = street_query(lat, lng, radius.ceil() as i32);
+    fetch_context(service, "streets", query, index).await.map(|elements| {
+        (
+            night_safety_summary(&elements, lat, lng, radius),
+            road_safety_summary(&elements, lat, lng, radius),
+        )
+    })
+}
+
+async fn fetch_nuisances(
+    service: &Arc<OverpassService>,
+    lat: f64,
+    lng: f64,
+    index: usize,
+) -> Option<Vec<NuisancePenalty>> {
+    let query = match nuisance_query(lat, lng) {
+        Some(query) => query,
+        None => return Some(Vec::new()),
+    };
+    fetch_context(service, "nuisances", query, index)
+        .await
+        .map(|elements| nuisance_penalties(&elements, lat, lng))
+}
+
+async fn area_facilities(
+    service: &Arc<OverpassService>,
+    locale: &Locale,
+    ring: &[(f64, f64)],
+    index: usize,
+) -> Result<(Vec<Facility>, AreaSummary), (StatusCode, String)> {
+    let bbox = pad_bounds(ring_bounds(ring), SEARCH_RADIUS as f64);
+    let queries: Vec<(String, String)> = CATEGORIES.iter().map(|&cat| {
+        (cat.to_string(), generate_overpass_bbox_query(&locale.patterns, cat, bbox))
+    }).collect();
+
+    let facilities_data = fetch_with_retry(service, queries)
+        .await
+        .map_err(|e| {
+            (StatusCode::SERVICE_UNAVAILABLE,
+                format!("Failed to fetch area data for location {}: {}", index + 1, e))
+        })?;
+
+    let classified: Vec<Facility> = classify_fetched(&facilities_data, &locale.patterns)
+        .into_iter()
         .fold(DeduplicationState::new(), |state, facility| state.add_facility(facility))
         .into_unique_facilities();
 
+    let facilities = measure_area_facilities(&classified, ring, MAX_FACILITY_DISTANCE);
+    let summary = area_summary(ring, &facilities);
+    Ok((facilities, summary))
+}
+
+pub async fn process_location_in(
+    service: &Arc<OverpassService>,
+    areas: &Mutex<AreaCache>,
+    options: &ScoringOptions,
+    loc: &SingleLocationRequest,
+    index: usize,
+    total: usize,
+) -> Result<LocationData, (StatusCode, String)> {
+    println!("Processing location {} of {}...", index + 1, total);
+
+    let target = resolve_target(service, loc, index).await?;
+    let locale = resolve_locale(target.lat, target.lng, loc.country.as_deref());
+    println!("Using language packs {:?} for location {}", locale.packs, index + 1);
+
+    let catchment = match target.ring {
+        Some(_) => MAX_FACILITY_DISTANCE,
+        None => catchment_distance(options, MAX_FACILITY_DISTANCE),
+    };
+
+    let essentials_reach = essentials_threshold(walking_speed(options));
+
+    // `candidates` keeps the unscored neighbourhood for the essentials check. Walking
+    // distances only exist for what the scoring pass reached, so walking mode and areas
+    // check against the scored facilities instead.
+    let (mut all_facilities, candidates, area, isochrones) = match &target.ring {
+        Some(ring) => {
+            let (facilities, summary) = area_facilities(service, &locale, ring, index).await?;
+            (facilities, None, Some(summary), None)
+        }
+        None => {
+            let radius = point_fetch_radius(options);
+            let candidates =
+                point_candidates(service, areas, &locale, target.lat, target.lng, radius, index).await?;
+            let facilities = score_candidates(&candidates, target.lat, target.lng, catchment);
+
+            if uses_walking_distance(options) || !isochrone_budgets(options).is_empty() {
+                let (facilities, isochrones) =
+                    walking_analysis(service, options, target.lat, target.lng, facilities, catchment, index).await?;
+                let candidates = (!uses_walking_distance(options)).then_some(candidates);
+                (facilities, candidates, None, isochrones)
+            } else {
+                (facilities, Some(candidates), None, None)
+            }
+        }
+    };
+
+    all_facilities.sort_by(cmp_by_distance);
     println!("✓ Processed {} unique facilities for location {}", all_facilities.len(), index + 1);
 
-    let (scores, facility_counts) = calculate_scores(&all_facilities);
+    // Three more Overpass fetches per location, so context layers are opt-in. They are
+    // measured around a point, so area targets report them as not computed.
+    let (green_space, nuisances, street_safety) = if options.context_analysis && target.ring.is_none() {
+        tokio::join!(
+            fetch_green_space(service, target.lat, target.lng, catchment, index),
+            fetch_nuisances(service, target.lat, target.lng, index),
+            fetch_street_safety(service, target.lat, target.lng, catchment, index),
+        )
+    } else {
+        (None, None, None)
+    };
+    let (night_safety, road_safety) = street_safety.unzip();
+    let computed = [green_space.is_some(), nuisances.is_some(), night_safety.is_some(), road_safety.is_some()];
+    let not_computed: Vec<String> = CONTEXT_LAYERS
+        .iter()
+        .zip(computed)
+        .filter(|&(_, computed)| options.context_analysis && !computed)
+        .map(|(layer, _)| layer.to_string())
+        .collect();
+    let adjustments: Vec<(&str, f64)> = green_space
+        .iter()
+        .map(|green| ("environment", green.environment_points))
+        .chain(night_safety.iter().map(|night| ("safety", night.safety_points)))
+        .chain(nuisances.iter().flatten().map(|n| (n.group.as_str(), -n.penalty)))
+        .collect();
+
+    let (scores, facility_counts) = calculate_adjusted_scores(&all_facilities, &adjustments);
+    let subcategory_counts = count_subcategories(&all_facilities);
+    let essentials = options.essentials.then(|| {
+        essentials_report(
+            candidates.as_deref().unwrap_or(&all_facilities),
+            essentials_reach,
+            walking_speed(options),
+        )
+    });
     
     let nearby_facilities: Vec<String> = all_facilities.iter()
         .take(MAX_NEARBY_FACILITIES)
@@ -162,17 +586,73 @@ async fn process_location(
         .collect();
     
     Ok(LocationData {   
-        address: format!("{}, {}", loc.lat, loc.lng),
+        address: target.address,
+        lat: target.lat,
+        lng: target.lng,
         facility_counts,
+        subcategory_counts,
         scores,
         nearby_facilities,
+        nearest_by_category: nearest_by_category(&all_facilities),
+        top_by_category: top_by_category(&all_facilities, options.top_n.unwrap_or_else(get_top_n)),
         facilities: all_facilities,
+        catchment: Some(Catchment {
+            radius_m: catchment,
+            distance_mode: if target.ring.is_none() && uses_walking_distance(options) {
+                DistanceMode::Walking
+            } else {
+                DistanceMode::Straight
+            },
+        }),
+        area,
+        isochrones,
+        essentials,
+        green_space,
+        nuisances,
+        night_safety,
+        road_safety,
+        not_computed,
     })
 }
 
+fn wants_geojson(params: &OutputParams, headers: &HeaderMap) -> bool {
+    let format_requested = params
+        .format
+        .as_deref()
+        .map(|f| f.eq_ignore_ascii_case("geojson"))
+        .unwrap_or(false);
+
+    let accept_geojson = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.contains(GEOJSON_CONTENT_TYPE))
+        .unwrap_or(false);
+
+    format_requested || accept_geojson
+}
+
 pub async fn calculate_score(
-    Json(payload): Json<CalculateScoreRequest>
-) -> Result<Json<Vec<LocationData>>, (StatusCode, String)> {
+    Query(params): Query<OutputParams>,
+    headers: HeaderMap,
+    Json(payload): Json<CalculateScoreRequest>,
+) -> Result<Response, (StatusCode, String)> {
+    let results = score_locations(&payload).await?;
+
+    if wants_geojson(&params, &headers) {
+        let collection = locations_feature_collection(
+            &results,
+            catchment_distance(&payload.options, SEARCH_RADIUS as f64),
+        );
+        return Ok((
+            [(header::CONTENT_TYPE, GEOJSON_CONTENT_TYPE)],
+            Json(collection),
+        ).into_response());
+    }
+
+    Ok(Json(results).into_response())
+}
+
+pub fn validate_locations(payload: &CalculateScoreRequest) -> Result<(), (StatusCode, String)> {
     println!("Received request with {} locations", payload.locations.len());
     
     if payload.locations.is_empty() {
@@ -180,118 +660,119 @@ pub async fn calculate_score(
     }
 
     for loc in &payload.locations {
-        if !(loc.lat >= -90.0 && loc.lat <= 90.0) {
+        if loc.point().is_none() && loc.polygon.is_none() && loc.relation.is_none() {
+            return Err((StatusCode::BAD_REQUEST,
+                "Each location needs lat/lng, a polygon or a relation id".to_string()));
+        }
+        if let Some(lat) = loc.lat.filter(|lat| !(*lat >= -90.0 && *lat <= 90.0)) {
             return Err((StatusCode::BAD_REQUEST, 
-                format!("Invalid latitude: {}. Must be between -90 and 90", loc.lat)));
+                format!("Invalid latitude: {}. Must be between -90 and 90", lat)));
         }
-        if !(loc.lng >= -180.0 && loc.lng <= 180.0) {
+        if let Some(lng) = loc.lng.filter(|lng| !(*lng >= -180.0 && *lng <= 180.0)) {
             return Err((StatusCode::BAD_REQUEST, 
-                format!("Invalid longitude: {}. Must be between -180 and 180", loc.lng)));
+                format!("Invalid longitude: {}. Must be between -180 and 180", lng)));
         }
     }
 
-    let overpass_service = Arc::new(OverpassService::new());
-    let total_locations = payload.locations.len();
-    
-    let results = stream::iter(payload.locations.iter().enumerate())
-        .fold(
-            Ok::<Vec<LocationData>, (StatusCode, String)>(Vec::new()),
-            |acc_result, (i, loc)| {
-                let service = overpass_service.clone();
-                let total = total_locations;
-                
-                async move {
-                    match acc_result {
-                        Ok(acc) => {
-                            let location_data = process_location(&service, loc, i, total).await?;
-                            
-                            if i < total - 1 {
-                                println!("Waiting 3 seconds before next location...");
-                                tokio::time::sleep(std::time::Duration::from_secs(3)).await;
-                            }
-                            
-                            Ok([acc, vec![location_data]].concat())
-                        }
-                        Err(e) => Err(e),
-                    }
-                }
-            }
-        )
-        .await?;
+    let options = &payload.options;
+    if let Some(minutes) = options.walk_minutes.filter(|m| !m.is_finite() || *m <= 0.0 || *m > MAX_WALK_MINUTES) {
+        return Err((StatusCode::BAD_REQUEST,
+            format!("Invalid walk_minutes: {}. Must be greater than 0 and at most {}", minutes, MAX_WALK_MINUTES)));
+    }
+    let (min_speed, max_speed) = WALKING_SPEED_RANGE_KMH;
+    if let Some(speed) = options.walking_speed_kmh.filter(|s| !s.is_finite() || *s < min_speed || *s > max_speed) {
+        return Err((StatusCode::BAD_REQUEST,
+            format!("Invalid walking_speed_kmh: {}. Must be between {} and {}", speed, min_speed, max_speed)));
+    }
+    if let Some(minutes) = options.isochrone_minutes.iter().flatten()
+        .find(|m| !m.is_finite() || **m <= 0.0 || **m > MAX_WALK_MINUTES) {
+        return Err((StatusCode::BAD_REQUEST,
+            format!("Invalid isochrone_minutes entry: {}. Must be greater than 0 and at most {}", minutes, MAX_WALK_MINUTES)));
+    }
+    if let Some(top_n) = options.top_n.filter(|n| *n > MAX_TOP_N) {
+        return Err((StatusCode::BAD_REQUEST,
+            format!("Invalid top_n: {}. Must be at most {}", top_n, MAX_TOP_N)));
+    }
 
-    Ok(Json(results))
+    Ok(())
 }
 
-fn parse_config_key(key: &str) -> Option<(&str, &str)> {
-    let parts: Vec<&str> = key.split('_').collect();
-    if parts.len() == 2 {
-        Some((parts[0], parts[1]))
-    } else {
-        None
+async fn fetch_shared_area(
+    service: &Arc<OverpassService>,
+    areas: &Mutex<AreaCache>,
+    area: &SharedArea,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    println!("Fetching one shared area for {} nearby locations...", area.members.len());
+
+    let queries: Vec<(String, String)> = CATEGORIES.iter().map(|&cat| {
+        (cat.to_string(), generate_overpass_bbox_query(&area.patterns, cat, area.bbox))
+    }).collect();
+
+    let facilities_data = fetch_with_retry(service, queries).await?;
+    let classified: Vec<Facility> = classify_fetched(&facilities_data, &area.patterns);
+
+    if let Ok(mut cache) = areas.lock() {
+        cache.insert(&area.packs, FetchedArea::Bounds(area.bbox), classified);
     }
+    Ok(())
 }
 
-fn build_single_query(
-    element_type: &str,
-    attribute: &str,
-    tags_str: &str,
-    distance: i32,
-    lat: f64,
-    lng: f64,
-) -> String {
-    format!(
-        r#"{}["{}"~"^({})$"](around:{},{},{});"#,
-        element_type, attribute, tags_str, distance, lat, lng
-    )
-}
+// Nearby locations are scored from one shared fetch, so the delay between locations
+// only applies before a location that has to go to Overpass again.
+pub fn location_stream(
+    locations: Vec<SingleLocationRequest>,
+    options: ScoringOptions,
+) -> impl Stream<Item = Result<(usize, LocationData), (StatusCode, String)>> {
+    let overpass_service = OVERPASS_SERVICE.clone();
+    let areas = Arc::new(Mutex::new(AreaCache::new(MAX_FACILITY_DISTANCE)));
+    let shared_areas = Arc::new(plan_shared_areas(&locations, point_fetch_radius(&options)));
+    let fetched_areas = Arc::new(Mutex::new(HashSet::new()));
+    let total_locations = locations.len();
+
+    stream::iter(locations.into_iter().enumerate()).then(move |(i, loc)| {
+        let service = overpass_service.clone();
+        let areas = areas.clone();
+        let shared_areas = shared_areas.clone();
+        let fetched_areas = fetched_areas.clone();
+        let options = options.clone();
+        let total = total_locations;
+
+        async move {
+            let shared = shared_areas.iter().position(|area| area.members.contains(&i));
+            let already_fetched = shared
+                .map(|a| fetched_areas.lock().map(|f| f.contains(&a)).unwrap_or(false))
+                .unwrap_or(false);
+
+            if i > 0 && !already_fetched {
+                println!("Waiting {} seconds before next location...", LOCATION_DELAY_SECS);
+                tokio::time::sleep(std::time::Duration::from_secs(LOCATION_DELAY_SECS)).await;
+            }
 
-fn extract_queries_from_config(
-    category_config: &Value,
-    lat: f64,
-    lng: f64,
-    distance: i32,
-) -> Vec<String> {
-    category_config
-        .as_object()
-        .map(|obj| {
-            obj.iter()
-                .filter(|(key, _)| *key != "description")
-                .filter_map(|(key, value)| {
-                    let tags_str = value.as_str()?;
-                    if tags_str.is_empty() {
-                        return None;
+            if let Some(a) = shared.filter(|_| !already_fetched) {
+                match fetch_shared_area(&service, &areas, &shared_areas[a]).await {
+                    Ok(()) => {
+                        if let Ok(mut fetched) = fetched_areas.lock() {
+                            fetched.insert(a);
+                        }
                     }
-                    
-                    let (element_type, attribute) = parse_config_key(key)?;
-                    Some(build_single_query(element_type, attribute, tags_str, distance, lat, lng))
-                })
-                .collect::<Vec<_>>()
-        })
-        .unwrap_or_default()
-}
-
-fn build_query_from_config(category: &str, lat: f64, lng: f64, distance: i32) -> Option<String> {
-    QUERY_CONFIG
-        .get("queries")
-        .and_then(|queries| queries.get(category))
-        .map(|category_config| extract_queries_from_config(category_config, lat, lng, distance))
-        .and_then(|queries| {
-            if queries.is_empty() {
-                None
-            } else {
-                Some(queries.join(" "))
+                    Err(e) => eprintln!("WARNING: Shared area fetch failed, fetching location {} alone: {}", i + 1, e),
+                }
             }
-        })
-}
 
-fn generate_overpass_query(category: &str, lat: f64, lng: f64) -> String {
-    let distance = SEARCH_RADIUS;
+            let location_data = process_location_in(&service, &areas, &options, &loc, i, total).await?;
+
+            Ok((i, location_data))
+        }
+    })
+}
 
-    let query_body = build_query_from_config(category, lat, lng, distance)
-        .unwrap_or_else(|| {
-            eprintln!("WARNING: Category '{}' not found in config, using default query", category);
-            format!(r#"node["amenity"](around:{},{},{});"#, distance, lat, lng)
-        });
+pub async fn score_locations(
+    payload: &CalculateScoreRequest,
+) -> Result<Vec<LocationData>, (StatusCode, String)> {
+    validate_locations(payload)?;
 
-    format!(r#"[out:json];({});out center;"#, query_body)
+    location_stream(payload.locations.clone(), payload.options.clone())
+        .map_ok(|(_, location_data)| location_data)
+        .try_collect()
+        .await
 }
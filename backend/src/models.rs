@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[macro_export]
 macro_rules! serde_clone {
@@ -28,10 +28,128 @@ macro_rules! serde_only {
 serde_clone!(
 pub struct LocationData {
     pub address: String,
+    pub lat: f64,
+    pub lng: f64,
     pub facility_counts: FacilityCounts,
+    pub subcategory_counts: BTreeMap<String, BTreeMap<String, usize>>,
     pub scores: Scores,
     pub nearby_facilities: Vec<String>,
+    #[serde(default)]
+    pub nearest_by_category: BTreeMap<String, NearbyFacility>,
+    #[serde(default)]
+    pub top_by_category: BTreeMap<String, Vec<NearbyFacility>>,
     pub facilities: Vec<Facility>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub catchment: Option<Catchment>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub area: Option<AreaSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub isochrones: Option<Vec<Isochrone>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub essentials: Option<EssentialsReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub green_space: Option<GreenSpace>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nuisances: Option<Vec<NuisancePenalty>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub night_safety: Option<NightSafety>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub road_safety: Option<RoadSafety>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub not_computed: Vec<String>,
+}
+);
+
+serde_clone!(
+pub struct NightSafety {
+    pub street_length_m: f64,
+    pub lit_length_m: f64,
+    pub lit_share: f64,
+    pub street_lamps: usize,
+    pub lamps_per_km: f64,
+    pub surveillance: usize,
+    pub score: f64,
+    pub safety_points: f64,
+}
+);
+
+serde_clone!(
+pub struct RoadSafety {
+    pub road_length_m: f64,
+    pub crossings: usize,
+    pub crossings_per_km: f64,
+    pub fast_road_share: f64,
+    pub unknown_speed_share: f64,
+    pub sidewalk_share: f64,
+    pub traffic_calming: usize,
+}
+);
+
+serde_clone!(
+pub struct NuisancePenalty {
+    pub kind: String,
+    pub group: String,
+    pub id: String,
+    pub name: Option<String>,
+    pub distance: f64,
+    pub penalty: f64,
+}
+);
+
+serde_clone!(
+pub struct GreenSpace {
+    pub green_area_m2: f64,
+    pub green_share: f64,
+    pub tree_count: usize,
+    pub environment_points: f64,
+}
+);
+
+serde_clone!(
+pub struct EssentialsReport {
+    pub threshold_m: f64,
+    pub threshold_minutes: f64,
+    pub completeness: f64,
+    pub checks: Vec<EssentialCheck>,
+}
+);
+
+serde_clone!(
+pub struct EssentialCheck {
+    pub name: String,
+    pub within_threshold: bool,
+    pub nearest_name: Option<String>,
+    pub nearest_distance: Option<f64>,
+}
+);
+
+serde_clone!(
+pub struct NearbyFacility {
+    pub id: String,
+    pub osm_url: String,
+    pub name: String,
+    pub subcategory: Option<String>,
+    pub lat: f64,
+    pub lng: f64,
+    pub distance: f64,
+}
+);
+
+serde_clone!(
+pub struct Isochrone {
+    pub minutes: f64,
+    pub distance_m: f64,
+    pub polygon: Vec<[f64; 2]>,
+}
+);
+
+serde_clone!(
+pub struct AreaSummary {
+    pub area_m2: f64,
+    pub perimeter_m: f64,
+    pub facilities_inside: usize,
+    pub density_per_km2: BTreeMap<String, f64>,
+    pub boundary: Vec<[f64; 2]>,
 }
 );
 
@@ -63,29 +181,111 @@ pub struct Scores {
 serde_clone!(
 pub struct Facility {
     pub id: String,
+    #[serde(default)]
+    pub osm_type: String,
+    #[serde(default)]
+    pub osm_url: String,
     pub name: String,
     pub category: String,
+    pub subcategory: Option<String>,
     pub lng: f64,
     pub lat: f64,
     pub distance: f64,
     pub contribution: f64,
-    pub tags: Option<HashMap<String, String>>,
+    pub tags: Option<BTreeMap<String, String>>,
+    // Closed [lng, lat] ring of facilities mapped as areas, when fetched with geometry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outline: Option<Vec<[f64; 2]>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub area_m2: Option<f64>,
 }
 );
 
 serde_only!(
 pub struct CalculateScoreRequest {
     pub locations: Vec<SingleLocationRequest>,
+    #[serde(flatten)]
+    pub options: ScoringOptions,
+}
+);
+
+serde_clone_default!(
+#[derive(Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DistanceMode {
+    #[default]
+    Straight,
+    Walking,
+}
+);
+
+// The reach facilities were scored within: a radius around a point, or around an
+// area's boundary.
+serde_clone!(
+pub struct Catchment {
+    pub radius_m: f64,
+    pub distance_mode: DistanceMode,
+}
+);
+
+serde_clone_default!(
+pub struct ScoringOptions {
+    #[serde(default)]
+    pub distance_mode: DistanceMode,
+    pub walk_minutes: Option<f64>,
+    pub walking_speed_kmh: Option<f64>,
+    #[serde(default)]
+    pub isochrones: bool,
+    pub isochrone_minutes: Option<Vec<f64>>,
+    pub top_n: Option<usize>,
+    #[serde(default)]
+    pub context_analysis: bool,
+    #[serde(default)]
+    pub essentials: bool,
+}
+);
+
+serde_only!(
+pub struct OutputParams {
+    pub format: Option<String>,
+}
+);
+
+serde_only!(
+pub struct ExportParams {
+    pub delimiter: Option<String>,
+}
+);
+
+serde_only!(
+pub struct HeatmapRequest {
+    pub bbox: Option<[f64; 4]>,
+    pub polygon: Option<serde_json::Value>,
+    pub cell_size: f64,
+    pub shape: Option<String>,
+    pub country: Option<String>,
 }
 );
 
 serde_clone!(
 pub struct SingleLocationRequest {
-    pub lat: f64,
-    pub lng: f64,
+    pub lat: Option<f64>,
+    pub lng: Option<f64>,
+    pub country: Option<String>,
+    pub polygon: Option<serde_json::Value>,
+    pub relation: Option<u64>,
 }
 );
 
+impl SingleLocationRequest {
+    pub fn point(&self) -> Option<(f64, f64)> {
+        match (self.lat, self.lng) {
+            (Some(lat), Some(lng)) if self.polygon.is_none() && self.relation.is_none() => Some((lat, lng)),
+            _ => None,
+        }
+    }
+}
+
 serde_only!(
 pub struct OverpassResponse {
     pub elements: Vec<OverpassElement>,
@@ -101,6 +301,19 @@ pub struct OverpassElement {
     pub lon: Option<f64>,
     pub center: Option<Center>,
     pub tags: Option<HashMap<String, String>>,
+    pub members: Option<Vec<OverpassMember>>,
+    pub nodes: Option<Vec<u64>>,
+    pub geometry: Option<Vec<Center>>,
+}
+);
+
+serde_clone!(
+pub struct OverpassMember {
+    #[serde(rename = "type")]
+    pub member_type: String,
+    #[serde(default)]
+    pub role: String,
+    pub geometry: Option<Vec<Center>>,
 }
 );
 
@@ -110,3 +323,42 @@ pub struct Center {
     pub lon: f64,
 }
 );
+
+serde_only!(
+pub struct LabeledCorpus {
+    pub default_country: Option<String>,
+    pub samples: Vec<CorpusSample>,
+}
+);
+
+serde_only!(
+pub struct CorpusSample {
+    pub name: Option<String>,
+    pub country: Option<String>,
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+    pub expected: Option<String>,
+}
+);
+
+serde_clone_default!(
+pub struct AccuracyBaseline {
+    pub accuracy: f64,
+}
+);
+
+serde_clone!(
+pub struct BatchPoint {
+    pub id: String,
+    pub lat: f64,
+    pub lng: f64,
+    pub country: Option<String>,
+}
+);
+
+serde_clone!(
+pub struct BatchRecord {
+    pub id: String,
+    pub location: LocationData,
+}
+);
@@ -0,0 +1,66 @@
+use axum::{
+    body::Body,
+    extract::Query,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures::stream::{self, StreamExt};
+
+use crate::models::{CalculateScoreRequest, ExportParams, LocationData};
+use crate::services::export::{
+    facility_header, facility_rows, parse_delimiter, score_header, score_row, UTF8_BOM,
+};
+use crate::{location_stream, validate_locations};
+
+const CSV_CONTENT_TYPE: &str = "text/csv; charset=utf-8";
+
+fn csv_response(
+    payload: CalculateScoreRequest,
+    filename: &str,
+    header_line: String,
+    row: fn(&str, &LocationData, char) -> String,
+    delimiter: char,
+) -> Response {
+    let head = stream::once(async move { Ok::<String, std::io::Error>(format!("{}{}", UTF8_BOM, header_line)) });
+
+    let rows = location_stream(payload.locations, payload.options).map(move |result| {
+        result
+            .map(|(i, location)| row(&(i + 1).to_string(), &location, delimiter))
+            .map_err(|(status, message)| {
+                eprintln!("CSV export aborted ({}): {}", status, message);
+                std::io::Error::other(message)
+            })
+    });
+
+    (
+        [
+            (header::CONTENT_TYPE, CSV_CONTENT_TYPE.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        Body::from_stream(head.chain(rows)),
+    )
+        .into_response()
+}
+
+pub async fn export_scores_csv(
+    Query(params): Query<ExportParams>,
+    Json(payload): Json<CalculateScoreRequest>,
+) -> Result<Response, (StatusCode, String)> {
+    let delimiter = parse_delimiter(params.delimiter.as_deref())
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    validate_locations(&payload)?;
+
+    Ok(csv_response(payload, "scores.csv", score_header(delimiter), score_row, delimiter))
+}
+
+pub async fn export_facilities_csv(
+    Query(params): Query<ExportParams>,
+    Json(payload): Json<CalculateScoreRequest>,
+) -> Result<Response, (StatusCode, String)> {
+    let delimiter = parse_delimiter(params.delimiter.as_deref())
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    validate_locations(&payload)?;
+
+    Ok(csv_response(payload, "facilities.csv", facility_header(delimiter), facility_rows, delimiter))
+}
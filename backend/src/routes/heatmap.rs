@@ -0,0 +1,160 @@
+use axum::{
+    extract::Query,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::models::{HeatmapRequest, OutputParams};
+use crate::services::geo::{bounds_span, pad_bounds, polygon_outer_ring, ring_bounds};
+use crate::services::heatmap::{cells_feature_collection, raster_response, score_cells, CellShape, Grid};
+use crate::services::language_packs::resolve_locale;
+use crate::services::query_builder::generate_overpass_bbox_query;
+use crate::services::score_calculator::classify_fetched;
+use crate::services::spatial_index::FacilityIndex;
+use crate::{fetch_with_retry, CATEGORIES, GEOJSON_CONTENT_TYPE, MAX_AREA_SPAN_M, OVERPASS_SERVICE, SEARCH_RADIUS};
+
+const MIN_CELL_SIZE: f64 = 50.0;
+const MAX_HEATMAP_CELLS: usize = 2500;
+
+fn parse_shape(value: Option<&str>) -> Result<CellShape, (StatusCode, String)> {
+    match value.map(|v| v.to_lowercase()).as_deref() {
+        None | Some("grid") | Some("square") => Ok(CellShape::Square),
+        Some("hexagon") | Some("hex") => Ok(CellShape::Hexagon),
+        Some(other) => Err((StatusCode::BAD_REQUEST,
+            format!("Unsupported cell shape '{}'. Use grid or hexagon", other))),
+    }
+}
+
+type Area = ([f64; 4], Option<Vec<(f64, f64)>>);
+
+fn resolve_area(payload: &HeatmapRequest) -> Result<Area, (StatusCode, String)> {
+    let polygon = payload
+        .polygon
+        .as_ref()
+        .map(|geometry| {
+            polygon_outer_ring(geometry).ok_or((StatusCode::BAD_REQUEST,
+                "polygon must be a GeoJSON Polygon geometry".to_string()))
+        })
+        .transpose()?;
+
+    let bbox = match (payload.bbox, &polygon) {
+        (Some(bbox), _) => bbox,
+        (None, Some(ring)) => ring_bounds(ring),
+        (None, None) => {
+            return Err((StatusCode::BAD_REQUEST, "Either bbox or polygon is required".to_string()))
+        }
+    };
+
+    let [min_lat, min_lng, max_lat, max_lng] = bbox;
+    if !(-90.0..=90.0).contains(&min_lat) || !(-90.0..=90.0).contains(&max_lat)
+        || !(-180.0..=180.0).contains(&min_lng) || !(-180.0..=180.0).contains(&max_lng)
+        || min_lat >= max_lat || min_lng >= max_lng
+    {
+        return Err((StatusCode::BAD_REQUEST,
+            "bbox must be [min_lat, min_lng, max_lat, max_lng] with valid, increasing coordinates".to_string()));
+    }
+
+    let (height, width) = bounds_span(bbox);
+    if height > MAX_AREA_SPAN_M || width > MAX_AREA_SPAN_M {
+        return Err((StatusCode::BAD_REQUEST,
+            format!("Heatmap area may span at most {} m per side", MAX_AREA_SPAN_M)));
+    }
+
+    Ok((bbox, polygon))
+}
+
+pub async fn generate_heatmap(
+    Query(params): Query<OutputParams>,
+    Json(payload): Json<HeatmapRequest>,
+) -> Result<Response, (StatusCode, String)> {
+    let shape = parse_shape(payload.shape.as_deref())?;
+    if !payload.cell_size.is_finite() || payload.cell_size < MIN_CELL_SIZE {
+        return Err((StatusCode::BAD_REQUEST,
+            format!("cell_size must be at least {} m", MIN_CELL_SIZE)));
+    }
+
+    let (bbox, polygon) = resolve_area(&payload)?;
+    let grid = Grid::new(shape, bbox, payload.cell_size);
+    if grid.cell_count() > MAX_HEATMAP_CELLS {
+        return Err((StatusCode::BAD_REQUEST,
+            format!("Heatmap would have {} cells; increase cell_size to stay under {}",
+                grid.cell_count(), MAX_HEATMAP_CELLS)));
+    }
+
+    let cells = grid.cells(polygon.as_deref());
+    println!("Heatmap: {} x {} grid, {} cells to score", grid.rows, grid.cols, cells.len());
+
+    let center_lat = (bbox[0] + bbox[2]) / 2.0;
+    let center_lng = (bbox[1] + bbox[3]) / 2.0;
+    let locale = resolve_locale(center_lat, center_lng, payload.country.as_deref());
+    // Facilities just outside the area still reach the edge cells, so the fetch is padded.
+    let fetch_area = pad_bounds(bbox, SEARCH_RADIUS as f64);
+
+    let queries: Vec<(String, String)> = CATEGORIES.iter().map(|&cat| {
+        (cat.to_string(), generate_overpass_bbox_query(&locale.patterns, cat, fetch_area))
+    }).collect();
+
+    let fetched = fetch_with_retry(&OVERPASS_SERVICE, queries).await.map_err(|e| {
+        (StatusCode::SERVICE_UNAVAILABLE, format!("Failed to fetch heatmap area: {}", e))
+    })?;
+
+    let radius = SEARCH_RADIUS as f64;
+    let mut index = FacilityIndex::new(radius);
+    index.extend(classify_fetched(&fetched, &locale.patterns));
+    let facility_total = index.facility_count();
+    println!("✓ Classified {} unique facilities for heatmap area", facility_total);
+
+    let scored = score_cells(cells, &index, radius);
+
+    let geojson = shape == CellShape::Hexagon
+        || params.format.as_deref().map(|f| f.eq_ignore_ascii_case("geojson")).unwrap_or(false);
+
+    if geojson {
+        return Ok((
+            [(header::CONTENT_TYPE, GEOJSON_CONTENT_TYPE)],
+            Json(cells_feature_collection(&grid, &scored)),
+        ).into_response());
+    }
+
+    Ok(Json(raster_response(&grid, &scored, facility_total)).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(body: serde_json::Value) -> HeatmapRequest {
+        serde_json::from_value(body).unwrap()
+    }
+
+    #[test]
+    fn bbox_crossing_the_antimeridian_is_rejected() {
+        let crossing = request(json!({ "bbox": [-17.0, 179.9, -16.9, -179.9], "cell_size": 100.0 }));
+        assert_eq!(resolve_area(&crossing).unwrap_err().0, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn polygon_alone_sets_the_bbox() {
+        let polygon = request(json!({
+            "polygon": { "type": "Polygon", "coordinates": [[[106.8, -6.2], [106.81, -6.2], [106.81, -6.19], [106.8, -6.2]]] },
+            "cell_size": 100.0
+        }));
+        let (bbox, ring) = resolve_area(&polygon).unwrap();
+        assert_eq!(bbox, [-6.2, 106.8, -6.19, 106.81]);
+        assert_eq!(ring.unwrap().len(), 4);
+    }
+
+    #[test]
+    fn missing_area_and_oversized_spans_are_rejected() {
+        assert!(resolve_area(&request(json!({ "cell_size": 100.0 }))).is_err());
+        assert!(resolve_area(&request(json!({ "bbox": [0.0, 0.0, 0.2, 0.2], "cell_size": 100.0 }))).is_err());
+    }
+
+    #[test]
+    fn unknown_shapes_are_rejected() {
+        assert!(parse_shape(Some("HEX")).is_ok_and(|shape| shape == CellShape::Hexagon));
+        assert!(parse_shape(Some("triangle")).is_err());
+    }
+}
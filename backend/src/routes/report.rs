@@ -0,0 +1,38 @@
+use axum::{
+    extract::Query,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::models::{CalculateScoreRequest, OutputParams};
+use crate::services::report::{render_html, render_pdf};
+use crate::score_locations;
+
+pub async fn generate_report(
+    Query(params): Query<OutputParams>,
+    Json(payload): Json<CalculateScoreRequest>,
+) -> Result<Response, (StatusCode, String)> {
+    let format = params.format.as_deref().unwrap_or("html").to_lowercase();
+    if format != "html" && format != "pdf" {
+        return Err((StatusCode::BAD_REQUEST,
+            format!("Unsupported report format '{}'. Use html or pdf", format)));
+    }
+
+    let results = score_locations(&payload).await?;
+
+    if format == "pdf" {
+        return Ok((
+            [
+                (header::CONTENT_TYPE, "application/pdf"),
+                (header::CONTENT_DISPOSITION, "attachment; filename=\"livability-report.pdf\""),
+            ],
+            render_pdf(&results),
+        ).into_response());
+    }
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/html; charset=utf-8")],
+        render_html(&results),
+    ).into_response())
+}
@@ -0,0 +1,305 @@
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+use crate::models::{AreaSummary, Center, Facility, OverpassElement};
+use crate::services::geo::{
+    calculate_distance, distance_to_ring, point_in_ring, ring_area_m2, ring_bounds, ring_perimeter_m,
+    ring_to_ring_distance,
+};
+use crate::services::score_calculator::measure_facility;
+
+type Ring = Vec<(f64, f64)>;
+
+fn join_segment(ring: &mut Ring, segments: &mut Vec<Ring>) -> bool {
+    let end = match ring.last() {
+        Some(&end) => end,
+        None => return false,
+    };
+
+    let next = segments.iter().position(|s| s.first() == Some(&end) || s.last() == Some(&end));
+    match next {
+        Some(i) => {
+            let mut segment = segments.swap_remove(i);
+            if segment.first() != Some(&end) {
+                segment.reverse();
+            }
+            ring.extend(segment.into_iter().skip(1));
+            true
+        }
+        None => false,
+    }
+}
+
+const OUTER_ROLES: &[&str] = &["outer", ""];
+const INNER_ROLES: &[&str] = &["inner"];
+
+fn member_segments(relation: &OverpassElement, roles: &[&str]) -> Option<Vec<Ring>> {
+    Some(
+        relation
+            .members
+            .as_ref()?
+            .iter()
+            .filter(|m| m.member_type == "way" && roles.contains(&m.role.as_str()))
+            .filter_map(|m| m.geometry.as_ref())
+            .map(|geometry| geometry.iter().map(|p| (p.lat, p.lon)).collect::<Ring>())
+            .filter(|segment| segment.len() >= 2)
+            .collect(),
+    )
+}
+
+// Member ways come in arbitrary order and direction; they are chained end to end and
+// only the rings that close are kept.
+fn closed_rings(mut segments: Vec<Ring>) -> Vec<Ring> {
+    let mut rings: Vec<Ring> = Vec::new();
+    while let Some(mut ring) = segments.pop() {
+        while ring.first() != ring.last() && join_segment(&mut ring, &mut segments) {}
+        if ring.len() >= 4 && ring.first() == ring.last() {
+            rings.push(ring);
+        }
+    }
+    rings
+}
+
+// Boundary relations can have several outer rings; the largest is taken as the outline.
+pub fn relation_outer_ring(relation: &OverpassElement) -> Option<Ring> {
+    closed_rings(member_segments(relation, OUTER_ROLES)?)
+        .into_iter()
+        .max_by(|a, b| ring_area_m2(a).total_cmp(&ring_area_m2(b)))
+}
+
+// Every outer ring of an area together with the holes cut out of it. Unlike
+// `element_outline` this keeps the whole shape, for measures that need true coverage.
+pub fn element_rings(element: &OverpassElement) -> Option<(Vec<Ring>, Vec<Ring>)> {
+    match element.element_type.as_str() {
+        "way" => element_outline(element).map(|ring| (vec![ring], Vec::new())),
+        "relation" => {
+            let outers = closed_rings(member_segments(element, OUTER_ROLES)?);
+            let inners = closed_rings(member_segments(element, INNER_ROLES)?);
+            (!outers.is_empty()).then_some((outers, inners))
+        }
+        _ => None,
+    }
+}
+
+// Closed ways and multipolygon relations fetched with `out geom` are areas; open ways
+// such as footways stay lines and are measured from their center.
+pub fn element_outline(element: &OverpassElement) -> Option<Ring> {
+    match element.element_type.as_str() {
+        "way" => element
+            .geometry
+            .as_ref()
+            .map(|geometry| geometry.iter().map(|p| (p.lat, p.lon)).collect::<Ring>())
+            .filter(|ring| ring.len() >= 4 && ring.first() == ring.last()),
+        "relation" => relation_outer_ring(element),
+        _ => None,
+    }
+}
+
+// Same point Overpass reports for `out center`: the middle of the bounding box.
+pub fn element_position(element: &OverpassElement) -> Option<(f64, f64)> {
+    if let (Some(lat), Some(lng)) = (element.lat, element.lon) {
+        return Some((lat, lng));
+    }
+    if let Some(center) = &element.center {
+        return Some((center.lat, center.lon));
+    }
+
+    let members = element.members.iter().flatten().filter_map(|m| m.geometry.as_ref());
+    let points: Ring = element
+        .geometry
+        .iter()
+        .chain(members)
+        .flatten()
+        .map(|p| (p.lat, p.lon))
+        .collect();
+    if points.is_empty() {
+        return None;
+    }
+    let [min_lat, min_lng, max_lat, max_lng] = ring_bounds(&points);
+    Some(((min_lat + max_lat) / 2.0, (min_lng + max_lng) / 2.0))
+}
+
+fn polyline_distance(lat: f64, lng: f64, line: &[Center]) -> Option<f64> {
+    match line {
+        [] => None,
+        [point] => Some(calculate_distance(lat, lng, point.lat, point.lon)),
+        _ => {
+            let points: Ring = line.iter().map(|p| (p.lat, p.lon)).collect();
+            Some(distance_to_ring(lat, lng, &points))
+        }
+    }
+}
+
+// Distance from a point to an element fetched with `out geom`: to the node itself, to
+// the nearest segment of a way or relation member, or zero from inside an area.
+pub fn element_distance(element: &OverpassElement, lat: f64, lng: f64) -> Option<f64> {
+    if let (Some(e_lat), Some(e_lng)) = (element.lat, element.lon) {
+        return Some(calculate_distance(lat, lng, e_lat, e_lng));
+    }
+    if element_outline(element).is_some_and(|ring| point_in_ring(lat, lng, &ring)) {
+        return Some(0.0);
+    }
+
+    let members = element.members.iter().flatten().filter_map(|m| m.geometry.as_ref());
+    element
+        .geometry
+        .iter()
+        .chain(members)
+        .filter_map(|line| polyline_distance(lat, lng, line))
+        .min_by(f64::total_cmp)
+        .or_else(|| {
+            let (e_lat, e_lng) = element_position(element)?;
+            Some(calculate_distance(lat, lng, e_lat, e_lng))
+        })
+}
+
+fn facility_ring(facility: &Facility) -> Option<Ring> {
+    facility
+        .outline
+        .as_ref()
+        .map(|outline| outline.iter().map(|&[lng, lat]| (lat, lng)).collect())
+}
+
+// Distance to the nearest edge of an outlined facility, zero from inside it.
+pub fn facility_distance(facility: &Facility, lat: f64, lng: f64) -> f64 {
+    match facility_ring(facility) {
+        Some(ring) if point_in_ring(lat, lng, &ring) => 0.0,
+        Some(ring) => distance_to_ring(lat, lng, &ring),
+        None => calculate_distance(lat, lng, facility.lat, facility.lng),
+    }
+}
+
+// How far an outline reaches beyond the facility's center point.
+pub fn outline_extent(facility: &Facility) -> f64 {
+    facility
+        .outline
+        .iter()
+        .flatten()
+        .map(|&[lng, lat]| calculate_distance(facility.lat, facility.lng, lat, lng))
+        .fold(0.0, f64::max)
+}
+
+// Gap between the scored area and a facility: outlined facilities count from their
+// nearest edge, so a park reaching into the area is inside it even if its center is not.
+fn distance_from_area(facility: &Facility, ring: &[(f64, f64)]) -> f64 {
+    match facility_ring(facility) {
+        Some(outline) => ring_to_ring_distance(&outline, ring),
+        None if point_in_ring(facility.lat, facility.lng, ring) => 0.0,
+        None => distance_to_ring(facility.lat, facility.lng, ring),
+    }
+}
+
+// Facilities inside or overlapping the polygon count at distance zero; outside ones are
+// measured to the nearest edge, so the usual distance decay applies around the boundary.
+pub fn measure_area_facilities(classified: &[Facility], ring: &[(f64, f64)], radius: f64) -> Vec<Facility> {
+    let scale = normalization_factor(ring, radius);
+
+    classified
+        .iter()
+        .filter_map(|facility| {
+            let distance = distance_from_area(facility, ring);
+            if distance > radius {
+                return None;
+            }
+            measure_facility(facility, distance, radius)
+        })
+        .map(|facility| Facility {
+            contribution: facility.contribution * scale,
+            ..facility
+        })
+        .collect()
+}
+
+// Scales contributions to what a standard point catchment of the same density would
+// collect, so large areas are not rewarded for their size alone.
+fn normalization_factor(ring: &[(f64, f64)], radius: f64) -> f64 {
+    let reference = PI * radius * radius;
+    let catchment = ring_area_m2(ring) + ring_perimeter_m(ring) * radius + reference;
+    (reference / catchment).min(1.0)
+}
+
+pub fn area_summary(ring: &[(f64, f64)], facilities: &[Facility]) -> AreaSummary {
+    let area_m2 = ring_area_m2(ring);
+    let inside: Vec<&Facility> = facilities.iter().filter(|f| f.distance == 0.0).collect();
+    let area_km2 = (area_m2 / 1_000_000.0).max(f64::EPSILON);
+
+    let density_per_km2 = inside
+        .iter()
+        .fold(BTreeMap::<String, usize>::new(), |mut counts, f| {
+            *counts.entry(f.category.clone()).or_insert(0) += 1;
+            counts
+        })
+        .into_iter()
+        .map(|(category, count)| (category, count as f64 / area_km2))
+        .collect();
+
+    AreaSummary {
+        area_m2,
+        perimeter_m: ring_perimeter_m(ring),
+        facilities_inside: inside.len(),
+        density_per_km2,
+        boundary: ring.iter().map(|&(lat, lng)| [lng, lat]).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // A 111 m square area at the equator, as a closed (lat, lng) ring.
+    fn area() -> Ring {
+        vec![(0.0, 0.0), (0.0, 0.001), (0.001, 0.001), (0.001, 0.0), (0.0, 0.0)]
+    }
+
+    fn facility(id: &str, lat: f64, lng: f64, outline: Option<Vec<[f64; 2]>>) -> Facility {
+        serde_json::from_value(json!({
+            "id": id, "name": id, "category": "recreation", "subcategory": "park",
+            "lat": lat, "lng": lng, "distance": 0.0, "contribution": 0.0, "outline": outline,
+        }))
+        .unwrap()
+    }
+
+    fn distances(facilities: &[Facility]) -> Vec<(&str, f64)> {
+        facilities.iter().map(|f| (f.id.as_str(), f.distance)).collect()
+    }
+
+    #[test]
+    fn outlines_reaching_into_the_area_count_as_inside() {
+        // Center 55 m east of the area, but the park's west edge lies inside it.
+        let park = facility(
+            "way/1",
+            0.0005,
+            0.0015,
+            Some(vec![[0.0008, 0.0004], [0.0022, 0.0004], [0.0022, 0.0006], [0.0008, 0.0006], [0.0008, 0.0004]]),
+        );
+        let measured = measure_area_facilities(&[park], &area(), 500.0);
+        assert_eq!(distances(&measured), vec![("way/1", 0.0)]);
+    }
+
+    #[test]
+    fn outlines_outside_the_area_are_measured_edge_to_edge() {
+        let park = facility(
+            "way/2",
+            0.0005,
+            0.003,
+            Some(vec![[0.002, 0.0004], [0.004, 0.0004], [0.004, 0.0006], [0.002, 0.0006], [0.002, 0.0004]]),
+        );
+        let measured = measure_area_facilities(&[park], &area(), 500.0);
+        assert!((measured[0].distance - 0.001 * 111_320.0).abs() < 2.0);
+    }
+
+    #[test]
+    fn points_use_containment_and_edge_distance() {
+        let facilities = vec![
+            facility("node/1", 0.0005, 0.0005, None),
+            facility("node/2", 0.0005, 0.002, None),
+            facility("node/3", 0.0005, 0.02, None),
+        ];
+        let measured = measure_area_facilities(&facilities, &area(), 500.0);
+        assert_eq!(measured.len(), 2);
+        assert_eq!(measured[0].distance, 0.0);
+        assert!((measured[1].distance - 0.001 * 111_320.0).abs() < 2.0);
+        assert!(measure_area_facilities(&[], &area(), 500.0).is_empty());
+    }
+}
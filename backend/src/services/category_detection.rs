@@ -35,10 +35,57 @@ fn get_bool(config: &Value, category: &str, key: &str, default: bool) -> bool {
         .unwrap_or(default)
 }
 
+// Boolean detector flags and their value when a rule omits them. Query derivation
+// reads the same table, so fetching and classification agree on omitted flags.
+pub const DETECTOR_FLAGS: &[(&str, &str, bool)] = &[
+    ("market", "shop_non_empty", true),
+    ("walkability", "lit_yes", true),
+    ("walkability", "traffic_calming_present", true),
+    ("safety", "lit_yes", true),
+    ("safety", "traffic_calming_present", true),
+    ("accessibility", "tactile_paving_yes", true),
+];
+
+pub fn flag_enabled(config: &Value, category: &str, flag: &str) -> bool {
+    let default = DETECTOR_FLAGS
+        .iter()
+        .find(|(c, f, _)| *c == category && *f == flag)
+        .is_some_and(|(_, _, default)| *default);
+    get_bool(config, category, flag, default)
+}
+
 fn name_contains_any(name: &str, patterns: &[String]) -> bool {
     patterns.iter().any(|p| name.contains(p))
 }
 
+fn is_excluded(config: &Value, scope: &str, tags: &HashMap<String, String>, name: &str) -> bool {
+    let exclusions = &config["exclusions"];
+    let tag_prefixes = get_list(exclusions, scope, "tag_prefix_present", &[]);
+    let name_prefixes = get_list(exclusions, scope, "name_prefixes", &[]);
+    let name_list = get_list(exclusions, scope, "name_contains", &[]);
+
+    let tag_prefix_match = tags
+        .keys()
+        .any(|key| tag_prefixes.iter().any(|p| key.starts_with(p)));
+
+    let tag_value_match = exclusions[scope]["tag_equals"]
+        .as_object()
+        .map(|rules| {
+            rules.keys().any(|key| {
+                let value = get_tag_as_str(tags, key);
+                get_list(&exclusions[scope], "tag_equals", key, &[])
+                    .iter()
+                    .any(|v| v == value)
+            })
+        })
+        .unwrap_or(false);
+
+    tag_prefix_match
+        || tag_value_match
+        || name_prefixes.iter().any(|p| name.starts_with(p))
+        || name_contains_any(name, &name_list)
+}
+
 fn is_education(config: &Value, tags: &HashMap<String, String>, name: &str) -> bool {
     let amenity = get_tag_as_str(tags, "amenity");
     let amenity_list = get_list(config, "education", "amenity_equals", &[]);
@@ -61,7 +108,7 @@ fn is_market(config: &Value, tags: &HashMap<String, String>, name: &str) -> bool
 
     let amenity_list = get_list(config, "market", "amenity_equals", &[]);
     let name_list = get_list(config, "market", "name_contains", &[]);
-    let shop_flag = get_bool(config, "market", "shop_non_empty", true);
+    let shop_flag = flag_enabled(config, "market", "shop_non_empty");
 
     (shop_flag && !shop.is_empty())
         || amenity_list.iter().any(|a| a == amenity)
@@ -133,8 +180,8 @@ fn is_walkability(config: &Value, tags: &HashMap<String, String>, _name: &str) -
     let amenity_list = get_list(config, "walkability", "amenity_equals", &[]);
     let natural_list = get_list(config, "walkability", "natural_equals", &[]);
     let landuse_list = get_list(config, "walkability", "landuse_equals", &[]);
-    let lit_yes = get_bool(config, "walkability", "lit_yes", true);
-    let traffic_flag = get_bool(config, "walkability", "traffic_calming_present", true);
+    let lit_yes = flag_enabled(config, "walkability", "lit_yes");
+    let traffic_flag = flag_enabled(config, "walkability", "traffic_calming_present");
 
     highway_list.iter().any(|v| v == highway)
         || route_list.iter().any(|v| v == route)
@@ -157,7 +204,7 @@ fn is_accessibility(config: &Value, tags: &HashMap<String, String>, _name: &str)
     let highway_list = get_list(config, "accessibility", "highway_equals", &[]);
     let wheelchair_list = get_list(config, "accessibility", "wheelchair_equals", &[]);
     let amenity_list = get_list(config, "accessibility", "amenity_equals", &[]);
-    let tactile_yes = get_bool(config, "accessibility", "tactile_paving_yes", true);
+    let tactile_yes = flag_enabled(config, "accessibility", "tactile_paving_yes");
     let tactile_paving = tags.get("tactile_paving")
         .map(|s| s.as_str())
         .map(|s| s == "yes")
@@ -179,8 +226,8 @@ fn is_safety(config: &Value, tags: &HashMap<String, String>, _name: &str) -> boo
     let man_made = get_tag_as_str(tags, "man_made");
 
     let highway_list = get_list(config, "safety", "highway_equals", &[]);
-    let lit_yes = get_bool(config, "safety", "lit_yes", true);
-    let traffic_flag = get_bool(config, "safety", "traffic_calming_present", true);
+    let lit_yes = flag_enabled(config, "safety", "lit_yes");
+    let traffic_flag = flag_enabled(config, "safety", "traffic_calming_present");
     let man_made_list = get_list(config, "safety", "man_made_equals", &[]);
     let amenity_list = get_list(config, "safety", "amenity_equals", &[]);
 
@@ -191,9 +238,12 @@ fn is_safety(config: &Value, tags: &HashMap<String, String>, _name: &str) -> boo
         || amenity_list.iter().any(|v| v == amenity)
 }
 
-pub fn detect_category(tags: &HashMap<String, String>, raw_name: &str) -> Option<&'static str> {
+pub fn detect_category(
+    cfg: &Value,
+    tags: &HashMap<String, String>,
+    raw_name: &str,
+) -> Option<&'static str> {
     let name = raw_name.to_lowercase();
-    let cfg = &PATTERN_CONFIG;
 
     type Detector = fn(&Value, &HashMap<String, String>, &str) -> bool;
     
@@ -210,8 +260,142 @@ pub fn detect_category(tags: &HashMap<String, String>, raw_name: &str) -> Option
         (is_safety, "safety"),
     ];
 
+    if is_excluded(cfg, "all", tags, &name) {
+        return None;
+    }
+
+    // An element excluded from the category it matches is dropped, not passed on to a
+    // weaker detector further down the list.
     detectors
         .iter()
         .find(|(detector, _)| detector(cfg, tags, &name))
         .map(|(_, category)| *category)
+        .filter(|category| !is_excluded(cfg, category, tags, &name))
+}
+
+fn as_list(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_str())
+                .map(|s| s.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn matches_subcategory_rule(rule: &Value, tags: &HashMap<String, String>, name: &str) -> bool {
+    let tag_match = rule
+        .as_object()
+        .map(|obj| {
+            obj.iter()
+                .filter_map(|(key, values)| key.strip_suffix("_equals").map(|tag| (tag, values)))
+                .any(|(tag, values)| {
+                    let value = get_tag_as_str(tags, tag);
+                    !value.is_empty() && as_list(values).iter().any(|v| v == value)
+                })
+        })
+        .unwrap_or(false);
+
+    let present_match = as_list(&rule["tags_present"])
+        .iter()
+        .any(|tag| !get_tag_as_str(tags, tag).is_empty());
+
+    tag_match || present_match || name_contains_any(name, &as_list(&rule["name_contains"]))
+}
+
+pub fn detect_subcategory(
+    cfg: &Value,
+    category: &str,
+    tags: &HashMap<String, String>,
+    raw_name: &str,
+) -> Option<String> {
+    let name = raw_name.to_lowercase();
+
+    cfg.get("subcategories")
+        .and_then(|subs| subs.get(category))
+        .and_then(|rules| rules.as_array())
+        .and_then(|rules| {
+            rules
+                .iter()
+                .find(|rule| matches_subcategory_rule(rule, tags, &name))
+                .and_then(|rule| rule["name"].as_str())
+                .map(|s| s.to_string())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn config() -> Value {
+        json!({
+            "market": { "amenity_equals": ["marketplace"], "name_contains": ["pasar"] },
+            "safety": { "lit_yes": true },
+            "exclusions": {
+                "all": { "tag_prefix_present": ["disused:"], "tag_equals": { "access": ["private"] } },
+                "market": { "tag_equals": { "shop": ["vacant"] }, "name_prefixes": ["bekas "] }
+            }
+        })
+    }
+
+    #[test]
+    fn excluded_element_does_not_fall_through_to_next_category() {
+        let vacant = tags(&[("shop", "vacant"), ("lit", "yes")]);
+        assert_eq!(detect_category(&config(), &vacant, ""), None);
+    }
+
+    #[test]
+    fn category_exclusion_by_name_prefix_is_case_insensitive() {
+        assert_eq!(detect_category(&config(), &tags(&[]), "Bekas Pasar Lama"), None);
+        assert_eq!(detect_category(&config(), &tags(&[]), "Pasar Lama"), Some("market"));
+    }
+
+    #[test]
+    fn global_exclusions_apply_before_any_detector() {
+        let disused = tags(&[("disused:amenity", "marketplace")]);
+        let private = tags(&[("amenity", "marketplace"), ("access", "private")]);
+        assert_eq!(detect_category(&config(), &disused, ""), None);
+        assert_eq!(detect_category(&config(), &private, ""), None);
+    }
+
+    #[test]
+    fn first_matching_detector_wins() {
+        let lit_market = tags(&[("amenity", "marketplace"), ("lit", "yes")]);
+        assert_eq!(detect_category(&config(), &lit_market, ""), Some("market"));
+        assert_eq!(detect_category(&config(), &tags(&[("lit", "yes")]), ""), Some("walkability"));
+    }
+
+    #[test]
+    fn empty_tags_and_name_match_nothing() {
+        assert_eq!(detect_category(&json!({}), &tags(&[]), ""), None);
+    }
+
+    #[test]
+    fn subcategory_rules_are_tried_in_order() {
+        let cfg = json!({
+            "subcategories": {
+                "health": [
+                    { "name": "hospital", "amenity_equals": ["hospital"], "name_contains": ["rumah sakit"] },
+                    { "name": "clinic", "tags_present": ["healthcare"] }
+                ]
+            }
+        });
+        let both = tags(&[("amenity", "hospital"), ("healthcare", "clinic")]);
+        assert_eq!(detect_subcategory(&cfg, "health", &both, "").as_deref(), Some("hospital"));
+        assert_eq!(detect_subcategory(&cfg, "health", &tags(&[("healthcare", "x")]), "").as_deref(), Some("clinic"));
+        assert_eq!(detect_subcategory(&cfg, "health", &tags(&[]), "Rumah Sakit Umum").as_deref(), Some("hospital"));
+    }
+
+    #[test]
+    fn missing_subcategory_rules_give_none() {
+        let cfg = json!({ "subcategories": { "health": [{ "name": "x", "amenity_equals": [""] }] } });
+        assert_eq!(detect_subcategory(&cfg, "health", &tags(&[]), ""), None);
+        assert_eq!(detect_subcategory(&cfg, "market", &tags(&[("shop", "x")]), ""), None);
+    }
 }
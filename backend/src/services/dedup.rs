@@ -0,0 +1,190 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::models::{Facility, OverpassElement};
+use crate::services::area_scoring::facility_distance;
+use crate::services::score_calculator::SCORING_CONFIG;
+
+const DEFAULT_MERGE_DISTANCE_M: f64 = 30.0;
+
+fn get_merge_distance() -> f64 {
+    SCORING_CONFIG["deduplication"]["merge_distance_m"]
+        .as_f64()
+        .unwrap_or(DEFAULT_MERGE_DISTANCE_M)
+}
+
+// One OSM object is returned by every category query whose filters it matches;
+// only its first occurrence is kept.
+pub fn unique_elements(fetched: &[(String, Vec<OverpassElement>)]) -> Vec<OverpassElement> {
+    let mut seen: HashSet<(&str, u64)> = HashSet::new();
+    fetched
+        .iter()
+        .flat_map(|(_category, elements)| elements)
+        .filter(|element| seen.insert((element.element_type.as_str(), element.id)))
+        .cloned()
+        .collect()
+}
+
+// Lowercased alphanumeric words, so "St. Mary's Hospital" and "st marys hospital" match.
+fn normalize_name(name: &str) -> String {
+    name.to_lowercase()
+        .replace(['\'', '’'], "")
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn tag_count(facility: &Facility) -> usize {
+    facility.tags.as_ref().map(|tags| tags.len()).unwrap_or(0)
+}
+
+// The better described object represents the place; ties keep the smaller id.
+fn preferred(kept: &Facility, candidate: &Facility) -> bool {
+    tag_count(candidate) > tag_count(kept)
+        || (tag_count(candidate) == tag_count(kept) && candidate.id < kept.id)
+}
+
+// Gap between two mapped objects, measured to the outline when either has one: a node
+// anywhere inside a large campus way is at the same place, however far from its center.
+fn separation(a: &Facility, b: &Facility) -> f64 {
+    facility_distance(a, b.lat, b.lng).min(facility_distance(b, a.lat, a.lng))
+}
+
+// A place mapped twice, such as a hospital node inside its building way, collapses to
+// one facility when both carry the same name, fall in the same category and lie within
+// the merge distance. Cross-category duplicates of one OSM object are already gone after
+// `unique_elements`; distinct objects in different categories are kept even when they
+// share a name, because they are different amenities at the same spot: the "Pasar Baru"
+// bus stop serves transport, the "Pasar Baru" market serves shopping, and a pharmacy
+// named after its hospital is still a pharmacy.
+// Unnamed facilities are never merged: two benches a few metres apart are two benches.
+pub fn merge_same_places(facilities: Vec<Facility>) -> Vec<Facility> {
+    let merge_distance = get_merge_distance();
+    let mut kept: Vec<Facility> = Vec::with_capacity(facilities.len());
+    let mut by_name: HashMap<(String, String), Vec<usize>> = HashMap::new();
+
+    facilities.into_iter().for_each(|facility| {
+        let name = facility
+            .tags
+            .as_ref()
+            .and_then(|tags| tags.get("name"))
+            .map(|name| normalize_name(name))
+            .filter(|name| !name.is_empty());
+
+        let key = match name {
+            Some(name) => (facility.category.clone(), name),
+            None => return kept.push(facility),
+        };
+
+        let same_place = by_name
+            .get(&key)
+            .and_then(|indices| indices.iter().copied().find(|&i| separation(&kept[i], &facility) <= merge_distance));
+
+        match same_place {
+            Some(i) if preferred(&kept[i], &facility) => kept[i] = facility,
+            Some(_) => {}
+            None => {
+                by_name.entry(key).or_default().push(kept.len());
+                kept.push(facility);
+            }
+        }
+    });
+
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Offsets in metres east and north of (0, 0).
+    fn facility(id: &str, category: &str, name: Option<&str>, east: f64, north: f64) -> Facility {
+        let tags = name.map(|name| json!({ "name": name })).unwrap_or(json!({}));
+        serde_json::from_value(json!({
+            "id": id,
+            "name": name.unwrap_or("Unnamed"),
+            "category": category,
+            "lat": north / 111_320.0,
+            "lng": east / 111_320.0,
+            "distance": 0.0,
+            "contribution": 0.0,
+            "tags": tags,
+        }))
+        .unwrap()
+    }
+
+    fn ids(facilities: &[Facility]) -> Vec<&str> {
+        facilities.iter().map(|f| f.id.as_str()).collect()
+    }
+
+    #[test]
+    fn duplicate_elements_keep_their_first_occurrence() {
+        let node: OverpassElement = serde_json::from_value(json!({ "type": "node", "id": 1 })).unwrap();
+        let way: OverpassElement = serde_json::from_value(json!({ "type": "way", "id": 1 })).unwrap();
+        let fetched = vec![
+            ("health".to_string(), vec![node.clone(), way]),
+            ("market".to_string(), vec![node]),
+        ];
+        let unique = unique_elements(&fetched);
+        assert_eq!(unique.len(), 2);
+        assert_eq!(unique_elements(&[]).len(), 0);
+    }
+
+    #[test]
+    fn same_name_and_category_nearby_merge_into_the_smaller_id() {
+        let merged = merge_same_places(vec![
+            facility("way/7", "health", Some("St. Mary's Hospital"), 0.0, 0.0),
+            facility("node/3", "health", Some("st marys hospital"), 10.0, 0.0),
+        ]);
+        assert_eq!(ids(&merged), vec!["node/3"]);
+    }
+
+    #[test]
+    fn better_tagged_objects_represent_the_place() {
+        let mut detailed = facility("way/9", "health", Some("Clinic"), 5.0, 0.0);
+        detailed.tags.as_mut().unwrap().insert("opening_hours".into(), "24/7".into());
+        let merged = merge_same_places(vec![facility("node/1", "health", Some("Clinic"), 0.0, 0.0), detailed]);
+        assert_eq!(ids(&merged), vec!["way/9"]);
+    }
+
+    #[test]
+    fn same_named_places_in_different_categories_stay_apart() {
+        let merged = merge_same_places(vec![
+            facility("way/1", "market", Some("Pasar Baru"), 0.0, 0.0),
+            facility("node/2", "transport", Some("Pasar Baru"), 5.0, 0.0),
+        ]);
+        assert_eq!(ids(&merged), vec!["way/1", "node/2"]);
+    }
+
+    #[test]
+    fn unnamed_and_distant_facilities_stay_apart() {
+        let merged = merge_same_places(vec![
+            facility("node/1", "walkability", None, 0.0, 0.0),
+            facility("node/2", "walkability", None, 1.0, 0.0),
+            facility("node/3", "health", Some("Clinic"), 0.0, 0.0),
+            facility("node/4", "health", Some("Clinic"), 100.0, 0.0),
+        ]);
+        assert_eq!(merged.len(), 4);
+        assert!(merge_same_places(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn nodes_inside_a_large_outline_merge_with_it() {
+        // A 200 m square campus; the node sits 80 m from its center but inside it.
+        let mut campus = facility("way/5", "health", Some("University Hospital"), 0.0, 0.0);
+        let corner = |east: f64, north: f64| [east / 111_320.0, north / 111_320.0];
+        campus.outline = Some(vec![
+            corner(-100.0, -100.0),
+            corner(100.0, -100.0),
+            corner(100.0, 100.0),
+            corner(-100.0, 100.0),
+            corner(-100.0, -100.0),
+        ]);
+        let merged = merge_same_places(vec![
+            campus,
+            facility("node/8", "health", Some("University Hospital"), 80.0, 0.0),
+        ]);
+        assert_eq!(ids(&merged), vec!["node/8"]);
+    }
+}
@@ -0,0 +1,183 @@
+use serde_json::Value;
+
+use crate::models::{EssentialCheck, EssentialsReport, Facility};
+use crate::services::score_calculator::SCORING_CONFIG;
+use crate::services::walking::{meters_to_minutes, minutes_to_meters};
+
+const DEFAULT_WALK_MINUTES: f64 = 10.0;
+
+const DEFAULT_ESSENTIALS: &[(&str, &str, &[&str])] = &[
+    ("grocery", "market", &["grocery", "supermarket"]),
+    ("pharmacy", "health", &["pharmacy"]),
+    ("primary_school", "education", &["primary"]),
+    ("clinic", "health", &["clinic", "doctor", "hospital"]),
+    ("transit_stop", "transport", &[]),
+    ("park", "recreation", &["park"]),
+];
+
+struct Essential {
+    name: String,
+    category: String,
+    // Empty means any facility of the category counts.
+    subcategories: Vec<String>,
+}
+
+impl Essential {
+    fn matches(&self, facility: &Facility) -> bool {
+        facility.category == self.category
+            && (self.subcategories.is_empty()
+                || facility
+                    .subcategory
+                    .as_ref()
+                    .is_some_and(|sub| self.subcategories.contains(sub)))
+    }
+}
+
+fn parse_essential(item: &Value) -> Option<Essential> {
+    Some(Essential {
+        name: item["name"].as_str()?.to_string(),
+        category: item["category"].as_str()?.to_string(),
+        subcategories: item["subcategories"]
+            .as_array()
+            .map(|arr| arr.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+            .unwrap_or_default(),
+    })
+}
+
+fn get_essentials(config: &Value) -> Vec<Essential> {
+    config["essentials"]["items"]
+        .as_array()
+        .map(|items| items.iter().filter_map(parse_essential).collect::<Vec<_>>())
+        .filter(|items| !items.is_empty())
+        .unwrap_or_else(|| {
+            DEFAULT_ESSENTIALS
+                .iter()
+                .map(|(name, category, subcategories)| Essential {
+                    name: name.to_string(),
+                    category: category.to_string(),
+                    subcategories: subcategories.iter().map(|s| s.to_string()).collect(),
+                })
+                .collect()
+        })
+}
+
+// How far an essential may be and still count as met, independent of the scoring catchment.
+pub fn essentials_threshold(speed_kmh: f64) -> f64 {
+    let minutes = SCORING_CONFIG["essentials"]["walk_minutes"]
+        .as_f64()
+        .unwrap_or(DEFAULT_WALK_MINUTES);
+    minutes_to_meters(minutes, speed_kmh)
+}
+
+fn check_essential(essential: &Essential, facilities: &[Facility], threshold_m: f64) -> EssentialCheck {
+    let nearest = facilities
+        .iter()
+        .filter(|f| essential.matches(f))
+        .min_by(|a, b| a.distance.total_cmp(&b.distance).then_with(|| a.id.cmp(&b.id)));
+
+    EssentialCheck {
+        name: essential.name.clone(),
+        within_threshold: nearest.is_some_and(|f| f.distance <= threshold_m),
+        nearest_name: nearest.map(|f| f.name.clone()),
+        nearest_distance: nearest.map(|f| f.distance),
+    }
+}
+
+// Pass/fail view next to the weighted scores: which everyday essentials can be reached
+// within the walking threshold at all. `facilities` should be everything classified around
+// the location, not just what scored, so unmet items still name their nearest match.
+pub fn essentials_report(facilities: &[Facility], threshold_m: f64, speed_kmh: f64) -> EssentialsReport {
+    let checks: Vec<EssentialCheck> = get_essentials(&SCORING_CONFIG)
+        .iter()
+        .map(|essential| check_essential(essential, facilities, threshold_m))
+        .collect();
+
+    let met = checks.iter().filter(|c| c.within_threshold).count();
+    let completeness = if checks.is_empty() {
+        0.0
+    } else {
+        met as f64 * 100.0 / checks.len() as f64
+    };
+
+    EssentialsReport {
+        threshold_m,
+        threshold_minutes: meters_to_minutes(threshold_m, speed_kmh),
+        completeness,
+        checks,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn facility(id: &str, category: &str, subcategory: Option<&str>, distance: f64) -> Facility {
+        serde_json::from_value(json!({
+            "id": id,
+            "name": id,
+            "category": category,
+            "subcategory": subcategory,
+            "lat": 0.0,
+            "lng": 0.0,
+            "distance": distance,
+            "contribution": 0.0,
+        }))
+        .unwrap()
+    }
+
+    fn check<'a>(report: &'a EssentialsReport, name: &str) -> &'a EssentialCheck {
+        report.checks.iter().find(|c| c.name == name).unwrap()
+    }
+
+    #[test]
+    fn threshold_follows_the_configured_walk_time() {
+        let minutes = SCORING_CONFIG["essentials"]["walk_minutes"].as_f64().unwrap();
+        assert_eq!(essentials_threshold(6.0), minutes * 100.0);
+    }
+
+    #[test]
+    fn matches_past_the_threshold_still_report_their_nearest() {
+        let facilities = vec![facility("node/1", "health", Some("pharmacy"), 900.0)];
+        let report = essentials_report(&facilities, 800.0, 4.8);
+        let pharmacy = check(&report, "pharmacy");
+        assert!(!pharmacy.within_threshold);
+        assert_eq!(pharmacy.nearest_name.as_deref(), Some("node/1"));
+        assert_eq!(pharmacy.nearest_distance, Some(900.0));
+    }
+
+    #[test]
+    fn unscored_facilities_still_count() {
+        // Zero contribution is how facilities beyond the scoring catchment arrive here.
+        let facilities = vec![facility("node/1", "transport", None, 700.0)];
+        let report = essentials_report(&facilities, 800.0, 4.8);
+        assert!(check(&report, "transit_stop").within_threshold);
+    }
+
+    #[test]
+    fn distance_ties_pick_the_smaller_id() {
+        let facilities = vec![
+            facility("way/9", "market", Some("grocery"), 300.0),
+            facility("node/2", "market", Some("supermarket"), 300.0),
+        ];
+        let report = essentials_report(&facilities, 800.0, 4.8);
+        assert_eq!(check(&report, "grocery").nearest_name.as_deref(), Some("node/2"));
+    }
+
+    #[test]
+    fn generic_schools_do_not_count_as_primary() {
+        let facilities = vec![facility("node/1", "education", Some("school"), 100.0)];
+        let report = essentials_report(&facilities, 800.0, 4.8);
+        let primary = check(&report, "primary_school");
+        assert!(!primary.within_threshold);
+        assert!(primary.nearest_name.is_none());
+    }
+
+    #[test]
+    fn empty_input_meets_nothing() {
+        let report = essentials_report(&[], 800.0, 4.8);
+        assert_eq!(report.completeness, 0.0);
+        assert!(report.checks.iter().all(|c| !c.within_threshold && c.nearest_distance.is_none()));
+        assert_eq!(report.threshold_minutes, 10.0);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::models::{Facility, LocationData};
+
+pub const UTF8_BOM: &str = "\u{feff}";
+const LINE_END: &str = "\r\n";
+
+pub fn parse_delimiter(value: Option<&str>) -> Result<char, String> {
+    match value.unwrap_or("comma") {
+        "comma" | "," => Ok(','),
+        "semicolon" | ";" => Ok(';'),
+        "tab" | "\t" => Ok('\t'),
+        other => Err(format!("Unsupported delimiter '{}'. Use comma, semicolon or tab", other)),
+    }
+}
+
+// Spreadsheet apps evaluate cells starting with these characters as formulas.
+// Plain numbers and coordinate text such as "-6.2, 106.8" are left readable: every
+// comma-separated part must parse as a number, so "-1-1" or "-2+3" are still escaped.
+fn neutralize_formula(field: &str) -> String {
+    let numeric_text = field.split(',').all(|part| part.trim().parse::<f64>().is_ok());
+    match field.chars().next() {
+        Some('=' | '+' | '-' | '@') if !numeric_text => format!("'{}", field),
+        _ => field.to_string(),
+    }
+}
+
+fn text_field(field: &str, delimiter: char) -> String {
+    let value = neutralize_formula(field);
+    if value.contains(delimiter) || value.contains('"') || value.contains('\n') || value.contains('\r') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+fn join_row(fields: Vec<String>, delimiter: char) -> String {
+    fields.join(&delimiter.to_string()) + LINE_END
+}
+
+pub fn score_header(delimiter: char) -> String {
+    let fields = [
+        "location_id", "address", "lat", "lng",
+        "overall", "services", "mobility", "safety", "environment",
+        "health_count", "education_count", "market_count", "transport_count",
+        "walkability_count", "recreation_count", "safety_count", "police_count",
+        "religious_count", "accessibility_count", "essentials_completeness",
+    ];
+    join_row(fields.iter().map(|f| f.to_string()).collect(), delimiter)
+}
+
+pub fn score_row(location_id: &str, location: &LocationData, delimiter: char) -> String {
+    let scores = &location.scores;
+    let counts = &location.facility_counts;
+
+    let fields = vec![
+        text_field(location_id, delimiter),
+        text_field(&location.address, delimiter),
+        location.lat.to_string(),
+        location.lng.to_string(),
+        format!("{:.2}", scores.overall),
+        format!("{:.2}", scores.services),
+        format!("{:.2}", scores.mobility),
+        format!("{:.2}", scores.safety),
+        format!("{:.2}", scores.environment),
+        counts.health.to_string(),
+        counts.education.to_string(),
+        counts.market.to_string(),
+        counts.transport.to_string(),
+        counts.walkability.to_string(),
+        counts.recreation.to_string(),
+        counts.safety.to_string(),
+        counts.police.to_string(),
+        counts.religious.to_string(),
+        counts.accessibility.to_string(),
+        location
+            .essentials
+            .as_ref()
+            .map(|e| format!("{:.1}", e.completeness))
+            .unwrap_or_default(),
+    ];
+    join_row(fields, delimiter)
+}
+
+pub fn facility_header(delimiter: char) -> String {
+    let fields = [
+        "location_id", "facility_id", "name", "category", "subcategory",
+        "lat", "lng", "distance", "contribution", "osm_type", "osm_url",
+    ];
+    join_row(fields.iter().map(|f| f.to_string()).collect(), delimiter)
+}
+
+fn facility_row(location_id: &str, facility: &Facility, delimiter: char) -> String {
+    let fields = vec![
+        text_field(location_id, delimiter),
+        text_field(&facility.id, delimiter),
+        text_field(&facility.name, delimiter),
+        text_field(&facility.category, delimiter),
+        text_field(facility.subcategory.as_deref().unwrap_or(""), delimiter),
+        facility.lat.to_string(),
+        facility.lng.to_string(),
+        format!("{:.1}", facility.distance),
+        format!("{:.3}", facility.contribution),
+        text_field(&facility.osm_type, delimiter),
+        text_field(&facility.osm_url, delimiter),
+    ];
+    join_row(fields, delimiter)
+}
+
+pub fn facility_rows(location_id: &str, location: &LocationData, delimiter: char) -> String {
+    location
+        .facilities
+        .iter()
+        .map(|facility| facility_row(location_id, facility, delimiter))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negative_coordinate_addresses_are_not_prefixed() {
+        assert_eq!(text_field("-6.2, 106.8", ';'), "-6.2, 106.8");
+        assert_eq!(text_field("-6.2, 106.8", ','), "\"-6.2, 106.8\"");
+        assert_eq!(text_field("-33.9,-70.6", ';'), "-33.9,-70.6");
+        assert_eq!(text_field("-12.5", ';'), "-12.5");
+    }
+
+    #[test]
+    fn formula_like_fields_are_neutralized() {
+        assert_eq!(text_field("=HYPERLINK(\"x\")", ';'), "\"'=HYPERLINK(\"\"x\"\")\"");
+        assert_eq!(text_field("+1 cmd", ';'), "'+1 cmd");
+        assert_eq!(text_field("-2+3", ';'), "'-2+3");
+        assert_eq!(text_field("-1-1", ';'), "'-1-1");
+        assert_eq!(text_field("-1,-1-1", ';'), "'-1,-1-1");
+        assert_eq!(text_field("- 1", ';'), "'- 1");
+        assert_eq!(text_field("@SUM(A1)", ';'), "'@SUM(A1)");
+    }
+
+    #[test]
+    fn delimiters_quotes_and_newlines_are_quoted() {
+        assert_eq!(text_field("a;b", ';'), "\"a;b\"");
+        assert_eq!(text_field("a;b", ','), "a;b");
+        assert_eq!(text_field("say \"hi\"", ','), "\"say \"\"hi\"\"\"");
+        assert_eq!(text_field("line\nbreak", '\t'), "\"line\nbreak\"");
+        assert_eq!(text_field("", ','), "");
+    }
+
+    #[test]
+    fn delimiter_names_and_characters_are_accepted() {
+        assert_eq!(parse_delimiter(None), Ok(','));
+        assert_eq!(parse_delimiter(Some("semicolon")), Ok(';'));
+        assert_eq!(parse_delimiter(Some("\t")), Ok('\t'));
+        assert!(parse_delimiter(Some("pipe")).is_err());
+    }
+
+    #[test]
+    fn rows_end_with_crlf() {
+        assert_eq!(join_row(vec!["a".into(), "b".into()], ';'), "a;b\r\n");
+        assert_eq!(join_row(Vec::new(), ';'), "\r\n");
+    }
+
+    #[test]
+    fn facility_rows_keep_existing_columns_in_place() {
+        let facility: Facility = serde_json::from_value(serde_json::json!({
+            "id": "way/42", "osm_type": "way", "osm_url": "https://www.openstreetmap.org/way/42",
+            "name": "Park", "category": "recreation", "lat": 1.5, "lng": 2.5,
+            "distance": 12.34, "contribution": 0.5,
+        }))
+        .unwrap();
+        let header = facility_header(',');
+        let row = facility_row("1", &facility, ',');
+        assert!(header.starts_with("location_id,facility_id,name,category,subcategory,lat,lng,distance,contribution,"));
+        assert!(header.ends_with("osm_type,osm_url\r\n"));
+        assert_eq!(row, "1,way/42,Park,recreation,,1.5,2.5,12.3,0.500,way,https://www.openstreetmap.org/way/42\r\n");
+    }
+}
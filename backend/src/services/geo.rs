@@ -0,0 +1,317 @@
+pub const EARTH_RADIUS_M: f64 = 6_371_000.0;
+
+pub fn calculate_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let d_lat = (lat2 - lat1).to_radians();
+    let d_lon = (lon2 - lon1).to_radians();
+
+    let a = (d_lat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos()
+            * lat2.to_radians().cos()
+            * (d_lon / 2.0).sin().powi(2);
+
+    let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
+    EARTH_RADIUS_M * c
+}
+
+pub fn destination_point(lat: f64, lng: f64, bearing_deg: f64, distance: f64) -> (f64, f64) {
+    let angular = distance / EARTH_RADIUS_M;
+    let bearing = bearing_deg.to_radians();
+    let lat1 = lat.to_radians();
+    let lng1 = lng.to_radians();
+
+    let lat2 = (lat1.sin() * angular.cos() + lat1.cos() * angular.sin() * bearing.cos()).asin();
+    let lng2 = lng1
+        + (bearing.sin() * angular.sin() * lat1.cos()).atan2(angular.cos() - lat1.sin() * lat2.sin());
+
+    (lat2.to_degrees(), lng2.to_degrees())
+}
+
+pub fn circle_ring(lat: f64, lng: f64, radius: f64, segments: usize) -> Vec<(f64, f64)> {
+    (0..=segments)
+        .map(|i| {
+            let bearing = 360.0 * (i % segments) as f64 / segments as f64;
+            destination_point(lat, lng, bearing, radius)
+        })
+        .collect()
+}
+
+pub const METERS_PER_DEGREE: f64 = 111_320.0;
+
+pub fn meters_per_degree_lng(lat: f64) -> f64 {
+    (METERS_PER_DEGREE * lat.to_radians().cos()).max(1.0)
+}
+
+// Ray casting on (lat, lng) pairs; adequate for city-scale rings away from the antimeridian.
+pub fn point_in_ring(lat: f64, lng: f64, ring: &[(f64, f64)]) -> bool {
+    ring.iter()
+        .zip(ring.iter().cycle().skip(1))
+        .filter(|((lat_a, lng_a), (lat_b, lng_b))| {
+            (lat_a > &lat) != (lat_b > &lat)
+                && lng < (lng_b - lng_a) * (lat - lat_a) / (lat_b - lat_a) + lng_a
+        })
+        .count()
+        % 2
+        == 1
+}
+
+// Outer ring of a GeoJSON Polygon geometry as (lat, lng) pairs. Every ring measure
+// expects the first point repeated at the end, so an unclosed ring is closed here.
+pub fn polygon_outer_ring(geometry: &serde_json::Value) -> Option<Vec<(f64, f64)>> {
+    if geometry["type"] != "Polygon" {
+        return None;
+    }
+    let mut ring: Vec<(f64, f64)> = geometry["coordinates"][0]
+        .as_array()?
+        .iter()
+        .map(|position| Some((position[1].as_f64()?, position[0].as_f64()?)))
+        .collect::<Option<_>>()?;
+    if ring.first() != ring.last() {
+        ring.push(ring[0]);
+    }
+    (ring.len() >= 4).then_some(ring)
+}
+
+pub fn ring_bounds(ring: &[(f64, f64)]) -> [f64; 4] {
+    ring.iter().fold(
+        [f64::MAX, f64::MAX, f64::MIN, f64::MIN],
+        |[min_lat, min_lng, max_lat, max_lng], &(lat, lng)| {
+            [min_lat.min(lat), min_lng.min(lng), max_lat.max(lat), max_lng.max(lng)]
+        },
+    )
+}
+
+pub fn pad_bounds(bbox: [f64; 4], padding: f64) -> [f64; 4] {
+    let [min_lat, min_lng, max_lat, max_lng] = bbox;
+    let d_lat = padding / METERS_PER_DEGREE;
+    let d_lng = padding / meters_per_degree_lng((min_lat + max_lat) / 2.0);
+    [
+        (min_lat - d_lat).max(-90.0),
+        (min_lng - d_lng).max(-180.0),
+        (max_lat + d_lat).min(90.0),
+        (max_lng + d_lng).min(180.0),
+    ]
+}
+
+pub fn bounds_span(bbox: [f64; 4]) -> (f64, f64) {
+    let [min_lat, min_lng, max_lat, max_lng] = bbox;
+    let height = (max_lat - min_lat) * METERS_PER_DEGREE;
+    let width = (max_lng - min_lng) * meters_per_degree_lng((min_lat + max_lat) / 2.0);
+    (height, width)
+}
+
+// Equirectangular offset in meters from an origin; accurate enough within a few km.
+pub fn local_xy(lat: f64, lng: f64, origin_lat: f64, origin_lng: f64) -> (f64, f64) {
+    (
+        (lng - origin_lng) * meters_per_degree_lng(origin_lat),
+        (lat - origin_lat) * METERS_PER_DEGREE,
+    )
+}
+
+fn projected_ring(ring: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let (origin_lat, origin_lng) = ring[0];
+    ring.iter()
+        .map(|&(lat, lng)| local_xy(lat, lng, origin_lat, origin_lng))
+        .collect()
+}
+
+fn signed_area(points: &[(f64, f64)]) -> f64 {
+    points
+        .windows(2)
+        .map(|w| w[0].0 * w[1].1 - w[1].0 * w[0].1)
+        .sum::<f64>()
+        / 2.0
+}
+
+pub fn ring_area_m2(ring: &[(f64, f64)]) -> f64 {
+    if ring.len() < 4 {
+        return 0.0;
+    }
+    signed_area(&projected_ring(ring)).abs()
+}
+
+pub fn ring_perimeter_m(ring: &[(f64, f64)]) -> f64 {
+    ring.windows(2)
+        .map(|w| calculate_distance(w[0].0, w[0].1, w[1].0, w[1].1))
+        .sum()
+}
+
+pub fn ring_centroid(ring: &[(f64, f64)]) -> (f64, f64) {
+    let (origin_lat, origin_lng) = ring[0];
+    let points = projected_ring(ring);
+    let area = signed_area(&points);
+
+    if area.abs() < f64::EPSILON {
+        let n = ring.len() as f64;
+        let (lat_sum, lng_sum) = ring.iter().fold((0.0, 0.0), |(a, b), &(lat, lng)| (a + lat, b + lng));
+        return (lat_sum / n, lng_sum / n);
+    }
+
+    let (cx, cy) = points.windows(2).fold((0.0, 0.0), |(cx, cy), w| {
+        let cross = w[0].0 * w[1].1 - w[1].0 * w[0].1;
+        (cx + (w[0].0 + w[1].0) * cross, cy + (w[0].1 + w[1].1) * cross)
+    });
+    let (x, y) = (cx / (6.0 * area), cy / (6.0 * area));
+
+    (
+        origin_lat + y / METERS_PER_DEGREE,
+        origin_lng + x / meters_per_degree_lng(origin_lat),
+    )
+}
+
+pub fn distance_to_segment(lat: f64, lng: f64, a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (ax, ay) = local_xy(a.0, a.1, lat, lng);
+    let (bx, by) = local_xy(b.0, b.1, lat, lng);
+    let (dx, dy) = (bx - ax, by - ay);
+    let length_sq = dx * dx + dy * dy;
+    let t = if length_sq > 0.0 {
+        (-(ax * dx + ay * dy) / length_sq).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    ((ax + t * dx).powi(2) + (ay + t * dy).powi(2)).sqrt()
+}
+
+pub fn distance_to_ring(lat: f64, lng: f64, ring: &[(f64, f64)]) -> f64 {
+    ring.windows(2)
+        .map(|w| distance_to_segment(lat, lng, w[0], w[1]))
+        .fold(f64::MAX, f64::min)
+}
+
+// Orientation signs survive the per-axis scaling to meters, so (lat, lng) works as is.
+fn segments_cross(a: (f64, f64), b: (f64, f64), c: (f64, f64), d: (f64, f64)) -> bool {
+    let side = |p: (f64, f64), q: (f64, f64), r: (f64, f64)| {
+        ((q.0 - p.0) * (r.1 - p.1) - (q.1 - p.1) * (r.0 - p.0)).signum()
+    };
+    side(a, b, c) * side(a, b, d) < 0.0 && side(c, d, a) * side(c, d, b) < 0.0
+}
+
+// Closest approach of two closed rings, zero when they overlap or one contains the other.
+pub fn ring_to_ring_distance(a: &[(f64, f64)], b: &[(f64, f64)]) -> f64 {
+    let contains_vertex = |outer: &[(f64, f64)], inner: &[(f64, f64)]| {
+        inner.iter().any(|&(lat, lng)| point_in_ring(lat, lng, outer))
+    };
+    let edges_cross = a.windows(2).any(|e| b.windows(2).any(|f| segments_cross(e[0], e[1], f[0], f[1])));
+    if contains_vertex(a, b) || contains_vertex(b, a) || edges_cross {
+        return 0.0;
+    }
+
+    let nearest = |from: &[(f64, f64)], to: &[(f64, f64)]| {
+        from.iter()
+            .map(|&(lat, lng)| distance_to_ring(lat, lng, to))
+            .fold(f64::MAX, f64::min)
+    };
+    nearest(a, b).min(nearest(b, a))
+}
+
+// Length of the part of segment a-b (local meters, circle at the origin) inside the circle.
+fn segment_length_within_circle(a: (f64, f64), b: (f64, f64), radius: f64) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_sq = dx * dx + dy * dy;
+    if length_sq == 0.0 {
+        return 0.0;
+    }
+    let half_b = a.0 * dx + a.1 * dy;
+    let c = a.0 * a.0 + a.1 * a.1 - radius * radius;
+    let discriminant = half_b * half_b - length_sq * c;
+    if discriminant <= 0.0 {
+        return 0.0;
+    }
+    let root = discriminant.sqrt();
+    let t_in = ((-half_b - root) / length_sq).max(0.0);
+    let t_out = ((-half_b + root) / length_sq).min(1.0);
+    (t_out - t_in).max(0.0) * length_sq.sqrt()
+}
+
+pub fn line_length_within_circle(line: &[(f64, f64)], lat: f64, lng: f64, radius: f64) -> f64 {
+    let points: Vec<(f64, f64)> = line.iter().map(|&(p_lat, p_lng)| local_xy(p_lat, p_lng, lat, lng)).collect();
+    points
+        .windows(2)
+        .map(|w| segment_length_within_circle(w[0], w[1], radius))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // About 111 m by 111 m at the equator, as closed (lat, lng) rings.
+    fn square() -> Vec<(f64, f64)> {
+        vec![(0.0, 0.0), (0.0, 0.001), (0.001, 0.001), (0.001, 0.0), (0.0, 0.0)]
+    }
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() <= tolerance
+    }
+
+    #[test]
+    fn unclosed_polygons_are_closed() {
+        let open = json!({ "type": "Polygon", "coordinates": [[[0.0, 0.0], [0.001, 0.0], [0.001, 0.001], [0.0, 0.001]]] });
+        let ring = polygon_outer_ring(&open).unwrap();
+        assert_eq!(ring.len(), 5);
+        assert_eq!(ring.first(), ring.last());
+        assert!(close(ring_area_m2(&ring), ring_area_m2(&square()), 1e-6));
+    }
+
+    #[test]
+    fn degenerate_and_non_polygon_geometries_are_rejected() {
+        let line = json!({ "type": "Polygon", "coordinates": [[[0.0, 0.0], [1.0, 0.0], [0.0, 0.0]]] });
+        let point = json!({ "type": "Point", "coordinates": [0.0, 0.0] });
+        let bad = json!({ "type": "Polygon", "coordinates": [[[0.0, "x"], [1.0, 0.0], [1.0, 1.0]]] });
+        assert!(polygon_outer_ring(&line).is_none());
+        assert!(polygon_outer_ring(&point).is_none());
+        assert!(polygon_outer_ring(&bad).is_none());
+    }
+
+    #[test]
+    fn ring_measures_match_the_square() {
+        let side = 0.001 * METERS_PER_DEGREE;
+        assert!(close(ring_area_m2(&square()), side * side, 1.0));
+        assert!(close(ring_perimeter_m(&square()), 4.0 * side, 1.0));
+        let (lat, lng) = ring_centroid(&square());
+        assert!(close(lat, 0.0005, 1e-9) && close(lng, 0.0005, 1e-9));
+        assert_eq!(ring_area_m2(&square()[..3]), 0.0);
+    }
+
+    #[test]
+    fn orientation_does_not_change_area() {
+        let reversed: Vec<(f64, f64)> = square().into_iter().rev().collect();
+        assert!(close(ring_area_m2(&reversed), ring_area_m2(&square()), 1e-6));
+    }
+
+    #[test]
+    fn point_in_ring_and_distance_to_edge() {
+        assert!(point_in_ring(0.0005, 0.0005, &square()));
+        assert!(!point_in_ring(0.0005, 0.0015, &square()));
+        assert!(close(distance_to_ring(0.0005, 0.002, &square()), 0.001 * METERS_PER_DEGREE, 1.0));
+        assert!(close(distance_to_ring(0.0005, 0.0005, &square()), 0.0005 * METERS_PER_DEGREE, 1.0));
+    }
+
+    #[test]
+    fn zero_length_segments_measure_to_their_point() {
+        assert!(close(distance_to_segment(0.0, 0.0, (0.0, 0.001), (0.0, 0.001)), 0.001 * METERS_PER_DEGREE, 1.0));
+    }
+
+    #[test]
+    fn bounds_are_padded_and_clamped() {
+        assert_eq!(ring_bounds(&square()), [0.0, 0.0, 0.001, 0.001]);
+        let [south, west, north, east] = pad_bounds([89.999, 179.999, 90.0, 180.0], 1000.0);
+        assert_eq!((north, east), (90.0, 180.0));
+        assert!(south < 89.999 && west < 179.999);
+    }
+
+    #[test]
+    fn ring_distances_are_zero_when_rings_overlap() {
+        let shifted = |d_lat: f64, d_lng: f64| -> Vec<(f64, f64)> {
+            square().into_iter().map(|(lat, lng)| (lat + d_lat, lng + d_lng)).collect()
+        };
+        let inner = vec![(0.0004, 0.0004), (0.0004, 0.0006), (0.0006, 0.0006), (0.0006, 0.0004), (0.0004, 0.0004)];
+        // A thin bar crossing the square with no vertex inside it.
+        let bar = vec![(0.0005, -0.001), (0.0005, 0.002), (0.0006, 0.002), (0.0006, -0.001), (0.0005, -0.001)];
+        assert_eq!(ring_to_ring_distance(&square(), &shifted(0.0005, 0.0005)), 0.0);
+        assert_eq!(ring_to_ring_distance(&square(), &inner), 0.0);
+        assert_eq!(ring_to_ring_distance(&inner, &square()), 0.0);
+        assert_eq!(ring_to_ring_distance(&square(), &bar), 0.0);
+        assert!(close(ring_to_ring_distance(&square(), &shifted(0.0, 0.002)), 0.001 * METERS_PER_DEGREE, 1.0));
+    }
+}
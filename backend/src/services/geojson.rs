@@ -0,0 +1,202 @@
+use serde_json::{json, Value};
+
+use crate::models::{Facility, Isochrone, LocationData};
+use crate::services::geo::circle_ring;
+
+const CIRCLE_SEGMENTS: usize = 64;
+
+fn point_geometry(lat: f64, lng: f64) -> Value {
+    json!({ "type": "Point", "coordinates": [lng, lat] })
+}
+
+fn origin_feature(location: &LocationData) -> Value {
+    json!({
+        "type": "Feature",
+        "geometry": point_geometry(location.lat, location.lng),
+        "properties": {
+            "kind": "origin",
+            "address": location.address,
+            "scores": location.scores,
+            "facility_counts": location.facility_counts,
+            "subcategory_counts": location.subcategory_counts,
+        }
+    })
+}
+
+fn search_area_feature(location: &LocationData, radius: f64) -> Value {
+    if let Some(area) = &location.area {
+        return json!({
+            "type": "Feature",
+            "geometry": { "type": "Polygon", "coordinates": [area.boundary] },
+            "properties": {
+                "kind": "search_area",
+                "area_m2": area.area_m2,
+                "perimeter_m": area.perimeter_m,
+                "facilities_inside": area.facilities_inside,
+                "density_per_km2": area.density_per_km2,
+            }
+        });
+    }
+
+    let ring: Vec<[f64; 2]> = circle_ring(location.lat, location.lng, radius, CIRCLE_SEGMENTS)
+        .into_iter()
+        .map(|(lat, lng)| [lng, lat])
+        .collect();
+
+    json!({
+        "type": "Feature",
+        "geometry": { "type": "Polygon", "coordinates": [ring] },
+        "properties": {
+            "kind": "search_area",
+            "radius": radius,
+        }
+    })
+}
+
+fn isochrone_feature(isochrone: &Isochrone) -> Value {
+    json!({
+        "type": "Feature",
+        "geometry": { "type": "Polygon", "coordinates": [isochrone.polygon] },
+        "properties": {
+            "kind": "isochrone",
+            "minutes": isochrone.minutes,
+            "distance_m": isochrone.distance_m,
+        }
+    })
+}
+
+fn facility_geometry(facility: &Facility) -> Value {
+    match &facility.outline {
+        Some(outline) => json!({ "type": "Polygon", "coordinates": [outline] }),
+        None => point_geometry(facility.lat, facility.lng),
+    }
+}
+
+fn facility_feature(facility: &Facility) -> Value {
+    json!({
+        "type": "Feature",
+        "id": facility.id,
+        "geometry": facility_geometry(facility),
+        "properties": {
+            "kind": "facility",
+            "osm_type": facility.osm_type,
+            "osm_url": facility.osm_url,
+            "name": facility.name,
+            "category": facility.category,
+            "subcategory": facility.subcategory,
+            "distance": facility.distance,
+            "contribution": facility.contribution,
+            "area_m2": facility.area_m2,
+            "tags": facility.tags,
+        }
+    })
+}
+
+fn location_features(location: &LocationData, radius: f64) -> Vec<Value> {
+    [origin_feature(location), search_area_feature(location, radius)]
+        .into_iter()
+        .chain(location.isochrones.iter().flatten().map(isochrone_feature))
+        .chain(location.facilities.iter().map(facility_feature))
+        .collect()
+}
+
+// GeoJSON has no collection of collections, so every location's features go into one
+// FeatureCollection and `location_index` tells them apart.
+pub fn locations_feature_collection(locations: &[LocationData], radius: f64) -> Value {
+    let features: Vec<Value> = locations
+        .iter()
+        .enumerate()
+        .flat_map(|(index, location)| {
+            location_features(location, radius).into_iter().map(move |mut feature| {
+                feature["properties"]["location_index"] = json!(index);
+                feature
+            })
+        })
+        .collect();
+
+    json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
+
+pub fn points_feature_collection(results: &[(String, LocationData)]) -> Value {
+    let features: Vec<Value> = results
+        .iter()
+        .map(|(id, location)| {
+            let mut feature = origin_feature(location);
+            feature["id"] = json!(id);
+            feature["properties"]["id"] = json!(id);
+            feature
+        })
+        .collect();
+
+    json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(lat: f64, facilities: Value) -> LocationData {
+        serde_json::from_value(json!({
+            "address": "test", "lat": lat, "lng": 106.8,
+            "facility_counts": {
+                "health": 0, "education": 0, "market": 0, "transport": 0, "walkability": 0,
+                "recreation": 0, "safety": 0, "police": 0, "religious": 0, "accessibility": 0
+            },
+            "subcategory_counts": {},
+            "scores": { "overall": 0.0, "services": 0.0, "mobility": 0.0, "safety": 0.0, "environment": 0.0 },
+            "nearby_facilities": [],
+            "facilities": facilities,
+        }))
+        .unwrap()
+    }
+
+    fn facility(outline: Option<Value>) -> Value {
+        json!({
+            "id": "way/1", "name": "Taman", "category": "recreation", "lat": -6.2, "lng": 106.8,
+            "distance": 0.0, "contribution": 1.0, "outline": outline,
+        })
+    }
+
+    #[test]
+    fn all_locations_share_one_feature_collection() {
+        let collection = locations_feature_collection(
+            &[location(-6.2, json!([facility(None)])), location(-6.3, json!([]))],
+            500.0,
+        );
+        assert_eq!(collection["type"], "FeatureCollection");
+        let indexes: Vec<u64> = collection["features"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| f["properties"]["location_index"].as_u64().unwrap())
+            .collect();
+        assert_eq!(indexes, vec![0, 0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn no_locations_give_an_empty_collection() {
+        let collection = locations_feature_collection(&[], 500.0);
+        assert_eq!(collection["features"], json!([]));
+    }
+
+    #[test]
+    fn search_circle_is_a_closed_ring() {
+        let feature = search_area_feature(&location(-6.2, json!([])), 500.0);
+        let ring = feature["geometry"]["coordinates"][0].as_array().unwrap();
+        assert_eq!(ring.first(), ring.last());
+        assert!(ring.len() > 4);
+    }
+
+    #[test]
+    fn outlined_facilities_are_polygons() {
+        let outline = json!([[106.8, -6.2], [106.801, -6.2], [106.801, -6.201], [106.8, -6.2]]);
+        assert_eq!(facility_feature(&serde_json::from_value(facility(Some(outline))).unwrap())["geometry"]["type"], "Polygon");
+        assert_eq!(facility_feature(&serde_json::from_value(facility(None)).unwrap())["geometry"]["type"], "Point");
+    }
+}
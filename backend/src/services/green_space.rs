@@ -0,0 +1,184 @@
+use std::f64::consts::PI;
+
+use crate::models::{GreenSpace, OverpassElement};
+use crate::services::area_scoring::element_rings;
+use crate::services::geo::{
+    calculate_distance, meters_per_degree_lng, point_in_ring, ring_bounds, METERS_PER_DEGREE,
+};
+use crate::services::score_calculator::SCORING_CONFIG;
+
+const DEFAULT_SHARE_WEIGHT: f64 = 40.0;
+const DEFAULT_TREE_WEIGHT: f64 = 0.5;
+const DEFAULT_MAX_TREE_POINTS: f64 = 10.0;
+const DEFAULT_GRID_CELLS: usize = 100;
+
+fn get_green_weights() -> (f64, f64, f64) {
+    let config = &SCORING_CONFIG["green_space"];
+    (
+        config["share_weight"].as_f64().unwrap_or(DEFAULT_SHARE_WEIGHT),
+        config["tree_weight"].as_f64().unwrap_or(DEFAULT_TREE_WEIGHT),
+        config["max_tree_points"].as_f64().unwrap_or(DEFAULT_MAX_TREE_POINTS),
+    )
+}
+
+fn get_grid_cells() -> usize {
+    SCORING_CONFIG["green_space"]["grid_cells"]
+        .as_u64()
+        .map(|n| n.max(1) as usize)
+        .unwrap_or(DEFAULT_GRID_CELLS)
+}
+
+type Ring = Vec<(f64, f64)>;
+
+// One mapped green area: its outer rings, its holes and the bounds of the outers.
+struct GreenArea {
+    outers: Vec<Ring>,
+    inners: Vec<Ring>,
+    bounds: [f64; 4],
+}
+
+impl GreenArea {
+    fn new((outers, inners): (Vec<Ring>, Vec<Ring>)) -> Self {
+        let points: Ring = outers.iter().flatten().copied().collect();
+        let bounds = ring_bounds(&points);
+        GreenArea { outers, inners, bounds }
+    }
+
+    fn contains(&self, lat: f64, lng: f64) -> bool {
+        let [min_lat, min_lng, max_lat, max_lng] = self.bounds;
+        (min_lat..=max_lat).contains(&lat)
+            && (min_lng..=max_lng).contains(&lng)
+            && self.outers.iter().any(|ring| point_in_ring(lat, lng, ring))
+            && !self.inners.iter().any(|ring| point_in_ring(lat, lng, ring))
+    }
+}
+
+// Cell centers of a square grid over the catchment, keeping those inside the circle.
+fn circle_samples(lat: f64, lng: f64, radius: f64, cells: usize) -> Vec<(f64, f64)> {
+    let size = 2.0 * radius / cells as f64;
+    let offset = |i: usize| -radius + (i as f64 + 0.5) * size;
+    (0..cells)
+        .flat_map(|row| (0..cells).map(move |col| (offset(col), offset(row))))
+        .filter(|(x, y)| x * x + y * y <= radius * radius)
+        .map(|(x, y)| (lat + y / METERS_PER_DEGREE, lng + x / meters_per_degree_lng(lat)))
+        .collect()
+}
+
+fn is_tree(element: &OverpassElement) -> bool {
+    element.element_type == "node"
+        && element
+            .tags
+            .as_ref()
+            .and_then(|tags| tags.get("natural"))
+            .is_some_and(|value| value == "tree")
+}
+
+// The catchment circle is sampled on a grid and a sample is green when any area covers
+// it outside that area's holes, so overlaps (grass inside a park) count once.
+pub fn green_space_summary(elements: &[OverpassElement], lat: f64, lng: f64, radius: f64) -> GreenSpace {
+    let circle_m2 = PI * radius * radius;
+    let areas: Vec<GreenArea> = elements.iter().filter_map(element_rings).map(GreenArea::new).collect();
+    let samples = circle_samples(lat, lng, radius, get_grid_cells());
+    let green_samples = samples
+        .iter()
+        .filter(|&&(s_lat, s_lng)| areas.iter().any(|area| area.contains(s_lat, s_lng)))
+        .count();
+
+    let green_share = if samples.is_empty() { 0.0 } else { green_samples as f64 / samples.len() as f64 };
+    let green_area_m2 = green_share * circle_m2;
+
+    let tree_count = elements
+        .iter()
+        .filter(|e| is_tree(e))
+        .filter_map(|e| Some((e.lat?, e.lon?)))
+        .filter(|&(t_lat, t_lng)| calculate_distance(lat, lng, t_lat, t_lng) <= radius)
+        .count();
+
+    let (share_weight, tree_weight, max_tree_points) = get_green_weights();
+
+    GreenSpace {
+        green_area_m2,
+        green_share,
+        tree_count,
+        environment_points: green_share * share_weight + (tree_count as f64 * tree_weight).min(max_tree_points),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    const RADIUS: f64 = 100.0;
+
+    // A closed square of `half` metres either side of (0, 0), as Overpass geometry.
+    fn square(half: f64) -> Value {
+        let d = half / METERS_PER_DEGREE;
+        json!([
+            { "lat": -d, "lon": -d }, { "lat": -d, "lon": d }, { "lat": d, "lon": d },
+            { "lat": d, "lon": -d }, { "lat": -d, "lon": -d }
+        ])
+    }
+
+    fn way(id: u64, half: f64) -> OverpassElement {
+        serde_json::from_value(json!({ "type": "way", "id": id, "geometry": square(half) })).unwrap()
+    }
+
+    fn tree(id: u64, north: f64) -> OverpassElement {
+        serde_json::from_value(json!({
+            "type": "node", "id": id, "lat": north / METERS_PER_DEGREE, "lon": 0.0,
+            "tags": { "natural": "tree" }
+        }))
+        .unwrap()
+    }
+
+    fn share(elements: &[OverpassElement]) -> f64 {
+        green_space_summary(elements, 0.0, 0.0, RADIUS).green_share
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 0.02
+    }
+
+    #[test]
+    fn overlapping_areas_count_once() {
+        // A 100 m square park covers 10000 / (PI * 100^2) of the circle.
+        let expected = 10_000.0 / (PI * RADIUS * RADIUS);
+        assert!(close(share(&[way(1, 50.0)]), expected));
+        assert!(close(share(&[way(1, 50.0), way(2, 50.0), way(3, 25.0)]), expected));
+    }
+
+    #[test]
+    fn areas_covering_the_whole_circle_cap_the_share() {
+        let summary = green_space_summary(&[way(1, 500.0)], 0.0, 0.0, RADIUS);
+        assert_eq!(summary.green_share, 1.0);
+        assert!(close(summary.green_area_m2 / (PI * RADIUS * RADIUS), 1.0));
+    }
+
+    #[test]
+    fn multipolygon_holes_are_not_green() {
+        let park: OverpassElement = serde_json::from_value(json!({
+            "type": "relation", "id": 5,
+            "members": [
+                { "type": "way", "ref": 1, "role": "outer", "geometry": square(50.0) },
+                { "type": "way", "ref": 2, "role": "inner", "geometry": square(25.0) }
+            ]
+        }))
+        .unwrap();
+        let expected = (10_000.0 - 2_500.0) / (PI * RADIUS * RADIUS);
+        assert!(close(share(&[park]), expected));
+    }
+
+    #[test]
+    fn trees_are_counted_inside_the_radius_only() {
+        let summary = green_space_summary(&[tree(1, 10.0), tree(2, 90.0), tree(3, 150.0)], 0.0, 0.0, RADIUS);
+        assert_eq!(summary.tree_count, 2);
+        assert_eq!(summary.green_share, 0.0);
+    }
+
+    #[test]
+    fn nothing_fetched_gives_no_points() {
+        let summary = green_space_summary(&[], 0.0, 0.0, RADIUS);
+        assert_eq!((summary.green_share, summary.tree_count, summary.environment_points), (0.0, 0, 0.0));
+    }
+}
@@ -0,0 +1,271 @@
+use rayon::prelude::*;
+use serde_json::{json, Value};
+
+use crate::models::{FacilityCounts, Scores};
+use crate::services::geo::{bounds_span, meters_per_degree_lng, point_in_ring, METERS_PER_DEGREE};
+use crate::services::score_calculator::{calculate_scores, measure_facilities, CONTEXT_LAYERS};
+use crate::services::spatial_index::FacilityIndex;
+
+const SCORE_FIELDS: [&str; 5] = ["overall", "services", "mobility", "safety", "environment"];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum CellShape {
+    Square,
+    Hexagon,
+}
+
+pub struct Cell {
+    pub row: usize,
+    pub col: usize,
+    pub lat: f64,
+    pub lng: f64,
+}
+
+pub struct ScoredCell {
+    pub cell: Cell,
+    pub scores: Scores,
+    pub facility_counts: FacilityCounts,
+}
+
+// Pointy-top hexagons: rows are 3/4 of a hexagon height apart.
+fn row_spacing(shape: CellShape, cell_size: f64) -> f64 {
+    match shape {
+        CellShape::Square => cell_size,
+        CellShape::Hexagon => cell_size * 3f64.sqrt() / 2.0,
+    }
+}
+
+pub struct Grid {
+    pub shape: CellShape,
+    pub bbox: [f64; 4],
+    pub cell_size: f64,
+    pub rows: usize,
+    pub cols: usize,
+}
+
+impl Grid {
+    pub fn new(shape: CellShape, bbox: [f64; 4], cell_size: f64) -> Self {
+        let (height, width) = bounds_span(bbox);
+        Grid {
+            shape,
+            bbox,
+            cell_size,
+            rows: ((height / row_spacing(shape, cell_size)).ceil() as usize).max(1),
+            cols: ((width / cell_size).ceil() as usize).max(1),
+        }
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.rows * self.cols
+    }
+
+    fn mid_lat(&self) -> f64 {
+        (self.bbox[0] + self.bbox[2]) / 2.0
+    }
+
+    // Rows run north to south like a raster; odd hexagon rows shift half a cell east.
+    fn center(&self, row: usize, col: usize) -> (f64, f64) {
+        let [_, min_lng, max_lat, _] = self.bbox;
+        let offset = match self.shape {
+            CellShape::Hexagon if row % 2 == 1 => 0.5,
+            _ => 0.0,
+        };
+        let lat = max_lat - (row as f64 + 0.5) * row_spacing(self.shape, self.cell_size) / METERS_PER_DEGREE;
+        let lng = min_lng + (col as f64 + 0.5 + offset) * self.cell_size / meters_per_degree_lng(self.mid_lat());
+        (lat, lng)
+    }
+
+    pub fn cells(&self, polygon: Option<&[(f64, f64)]>) -> Vec<Cell> {
+        (0..self.rows)
+            .flat_map(|row| (0..self.cols).map(move |col| (row, col)))
+            .map(|(row, col)| {
+                let (lat, lng) = self.center(row, col);
+                Cell { row, col, lat, lng }
+            })
+            .filter(|cell| polygon.is_none_or(|ring| point_in_ring(cell.lat, cell.lng, ring)))
+            .collect()
+    }
+
+    fn cell_ring(&self, cell: &Cell) -> Vec<[f64; 2]> {
+        let lat_scale = METERS_PER_DEGREE;
+        let lng_scale = meters_per_degree_lng(self.mid_lat());
+        let corners: Vec<(f64, f64)> = match self.shape {
+            CellShape::Square => {
+                let half = self.cell_size / 2.0;
+                vec![(-half, -half), (half, -half), (half, half), (-half, half)]
+            }
+            CellShape::Hexagon => {
+                let radius = self.cell_size / 3f64.sqrt();
+                (0..6)
+                    .map(|i| {
+                        let angle = (30.0 + 60.0 * i as f64).to_radians();
+                        (radius * angle.cos(), radius * angle.sin())
+                    })
+                    .collect()
+            }
+        };
+
+        corners
+            .iter()
+            .chain(corners.first())
+            .map(|(dx, dy)| [cell.lng + dx / lng_scale, cell.lat + dy / lat_scale])
+            .collect()
+    }
+}
+
+// Cells are scored from facilities alone: the context layers would cost several more
+// Overpass fetches per cell, so responses list them under `not_computed`.
+pub fn score_cells(cells: Vec<Cell>, index: &FacilityIndex, radius: f64) -> Vec<ScoredCell> {
+    cells
+        .into_par_iter()
+        .map(|cell| {
+            let facilities = measure_facilities(index.within(cell.lat, cell.lng, radius), cell.lat, cell.lng, radius);
+            let (scores, facility_counts) = calculate_scores(&facilities);
+            ScoredCell { cell, scores, facility_counts }
+        })
+        .collect()
+}
+
+fn score_value(scores: &Scores, field: &str) -> f64 {
+    match field {
+        "overall" => scores.overall,
+        "services" => scores.services,
+        "mobility" => scores.mobility,
+        "safety" => scores.safety,
+        _ => scores.environment,
+    }
+}
+
+// Cells outside the requested polygon stay null in every layer.
+pub fn raster_response(grid: &Grid, scored: &[ScoredCell], facility_total: usize) -> Value {
+    let empty_layer = vec![vec![Value::Null; grid.cols]; grid.rows];
+    let layers: serde_json::Map<String, Value> = SCORE_FIELDS
+        .iter()
+        .map(|&field| {
+            let layer = scored.iter().fold(empty_layer.clone(), |mut layer, s| {
+                layer[s.cell.row][s.cell.col] = json!(score_value(&s.scores, field));
+                layer
+            });
+            (field.to_string(), json!(layer))
+        })
+        .collect();
+
+    json!({
+        "type": "grid",
+        "bbox": grid.bbox,
+        "cell_size": grid.cell_size,
+        "rows": grid.rows,
+        "cols": grid.cols,
+        "row_order": "north_to_south",
+        "cells_scored": scored.len(),
+        "facilities_fetched": facility_total,
+        "not_computed": CONTEXT_LAYERS,
+        "layers": layers,
+    })
+}
+
+pub fn cells_feature_collection(grid: &Grid, scored: &[ScoredCell]) -> Value {
+    let features: Vec<Value> = scored
+        .iter()
+        .map(|s| {
+            json!({
+                "type": "Feature",
+                "id": format!("{}-{}", s.cell.row, s.cell.col),
+                "geometry": { "type": "Polygon", "coordinates": [grid.cell_ring(&s.cell)] },
+                "properties": {
+                    "kind": "heatmap_cell",
+                    "row": s.cell.row,
+                    "col": s.cell.col,
+                    "center": [s.cell.lng, s.cell.lat],
+                    "scores": s.scores,
+                    "facility_counts": s.facility_counts,
+                }
+            })
+        })
+        .collect();
+
+    json!({
+        "type": "FeatureCollection",
+        "bbox": [grid.bbox[1], grid.bbox[0], grid.bbox[3], grid.bbox[2]],
+        "not_computed": CONTEXT_LAYERS,
+        "features": features,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Just under 1 km by 1 km at the equator.
+    const BBOX: [f64; 4] = [0.0, 0.0, 0.0089, 0.0089];
+
+    #[test]
+    fn square_grid_covers_the_bbox() {
+        let grid = Grid::new(CellShape::Square, BBOX, 250.0);
+        assert_eq!((grid.rows, grid.cols), (4, 4));
+        let first = &grid.cells(None)[0];
+        assert_eq!((first.row, first.col), (0, 0));
+        assert!(first.lat > BBOX[2] - 0.002 && first.lng < BBOX[1] + 0.002);
+        assert_eq!(grid.cells(None).len(), grid.cell_count());
+    }
+
+    #[test]
+    fn tiny_bbox_still_has_one_cell() {
+        let grid = Grid::new(CellShape::Hexagon, [0.0, 0.0, 0.000_01, 0.000_01], 500.0);
+        assert_eq!(grid.cell_count(), 1);
+        assert_eq!(grid.cells(None).len(), 1);
+    }
+
+    #[test]
+    fn odd_hexagon_rows_shift_half_a_cell_east() {
+        let grid = Grid::new(CellShape::Hexagon, BBOX, 250.0);
+        let (_, even) = grid.center(0, 0);
+        let (_, odd) = grid.center(1, 0);
+        let half_cell = 125.0 / meters_per_degree_lng(grid.mid_lat());
+        assert!((odd - even - half_cell).abs() < 1e-9);
+        assert!(grid.rows > Grid::new(CellShape::Square, BBOX, 250.0).rows);
+    }
+
+    #[test]
+    fn cell_rings_are_closed() {
+        [CellShape::Square, CellShape::Hexagon].into_iter().for_each(|shape| {
+            let grid = Grid::new(shape, BBOX, 250.0);
+            let ring = grid.cell_ring(&grid.cells(None)[0]);
+            assert_eq!(ring.first(), ring.last());
+            assert_eq!(ring.len(), if shape == CellShape::Square { 5 } else { 7 });
+        });
+    }
+
+    #[test]
+    fn polygon_keeps_only_cells_inside_and_raster_nulls_the_rest() {
+        let grid = Grid::new(CellShape::Square, BBOX, 250.0);
+        // South-west half of the bbox; raster row 0 is the northern edge.
+        let triangle = [(0.0, 0.0), (0.0089, 0.0), (0.0, 0.0089), (0.0, 0.0)];
+        let cells = grid.cells(Some(&triangle));
+        assert!(!cells.is_empty() && cells.len() < grid.cell_count());
+
+        let scored: Vec<ScoredCell> = cells
+            .into_iter()
+            .map(|cell| ScoredCell { cell, scores: Scores::default(), facility_counts: FacilityCounts::default() })
+            .collect();
+        let raster = raster_response(&grid, &scored, 0);
+        assert_eq!(raster["layers"]["overall"][3][0], json!(0.0));
+        assert_eq!(raster["layers"]["overall"][0][3], Value::Null);
+    }
+
+    #[test]
+    fn empty_index_scores_zero() {
+        let grid = Grid::new(CellShape::Square, BBOX, 500.0);
+        let scored = score_cells(grid.cells(None), &FacilityIndex::new(500.0), 500.0);
+        assert_eq!(scored.len(), 4);
+        assert!(scored.iter().all(|s| s.scores.overall == 0.0));
+    }
+
+    #[test]
+    fn responses_mark_context_layers_as_not_computed() {
+        let grid = Grid::new(CellShape::Square, BBOX, 250.0);
+        let expected = json!(CONTEXT_LAYERS);
+        assert_eq!(raster_response(&grid, &[], 0)["not_computed"], expected);
+        assert_eq!(cells_feature_collection(&grid, &[])["not_computed"], expected);
+    }
+}
@@ -0,0 +1,214 @@
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+
+use crate::services::category_detection::PATTERN_CONFIG;
+
+pub static LANGUAGE_PACKS: Lazy<Value> = Lazy::new(|| {
+    std::fs::read_to_string("config/language_packs.json")
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or(json!({}))
+});
+
+pub struct Locale {
+    pub packs: Vec<String>,
+    pub patterns: Value,
+}
+
+fn as_string_list(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_str())
+                .map(|s| s.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn bbox_contains(bbox: &Value, lat: f64, lng: f64) -> Option<f64> {
+    let coords: Vec<f64> = bbox.as_array()?.iter().filter_map(|v| v.as_f64()).collect();
+    match coords.as_slice() {
+        [min_lat, min_lng, max_lat, max_lng]
+            if lat >= *min_lat && lat <= *max_lat && lng >= *min_lng && lng <= *max_lng =>
+        {
+            Some((max_lat - min_lat) * (max_lng - min_lng))
+        }
+        _ => None,
+    }
+}
+
+fn packs_for_country_code(config: &Value, code: &str) -> Option<Vec<String>> {
+    config["countries"]
+        .get(code.to_uppercase())
+        .map(|country| as_string_list(&country["packs"]))
+        .filter(|packs| !packs.is_empty())
+}
+
+// Country boxes overlap around borders (e.g. Borneo, Riau), and a bbox cannot tell
+// whose side of the border a point is on, so every containing box contributes its
+// packs. The tightest box comes first.
+fn packs_for_coordinates(config: &Value, lat: f64, lng: f64) -> Option<Vec<String>> {
+    let mut containing: Vec<(f64, &Value)> = config["countries"]
+        .as_object()?
+        .values()
+        .filter_map(|country| bbox_contains(&country["bbox"], lat, lng).map(|area| (area, country)))
+        .collect();
+    containing.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+    let packs = containing
+        .iter()
+        .flat_map(|(_, country)| as_string_list(&country["packs"]))
+        .fold(Vec::new(), |mut acc: Vec<String>, pack| {
+            if !acc.contains(&pack) {
+                acc.push(pack);
+            }
+            acc
+        });
+    Some(packs).filter(|packs| !packs.is_empty())
+}
+
+fn select_packs(config: &Value, lat: f64, lng: f64, country: Option<&str>) -> Vec<String> {
+    country
+        .and_then(|code| {
+            let packs = packs_for_country_code(config, code);
+            if packs.is_none() {
+                eprintln!("WARNING: Unknown country code '{}', falling back to coordinates", code);
+            }
+            packs
+        })
+        .or_else(|| packs_for_coordinates(config, lat, lng))
+        .unwrap_or_else(|| as_string_list(&config["default_packs"]))
+}
+
+fn merge_value(base: &Value, overlay: &Value) -> Value {
+    match (base, overlay) {
+        (Value::Object(b), Value::Object(o)) => {
+            let merged = o.iter().fold(b.clone(), |mut acc, (key, value)| {
+                let entry = acc
+                    .get(key)
+                    .map(|existing| merge_value(existing, value))
+                    .unwrap_or_else(|| value.clone());
+                acc.insert(key.clone(), entry);
+                acc
+            });
+            Value::Object(merged)
+        }
+        (Value::Array(b), Value::Array(o)) => {
+            let merged = o.iter().fold(b.clone(), |mut acc, v| {
+                if !acc.contains(v) {
+                    acc.push(v.clone());
+                }
+                acc
+            });
+            Value::Array(merged)
+        }
+        (_, overlay) => overlay.clone(),
+    }
+}
+
+fn merge_pack_section(config: &Value, base: &Value, packs: &[String], section: &str) -> Value {
+    packs
+        .iter()
+        .filter_map(|pack| {
+            let section_value = config["packs"].get(pack).map(|p| &p[section]);
+            if section_value.is_none() {
+                eprintln!("WARNING: Language pack '{}' not found in config", pack);
+            }
+            section_value
+        })
+        .filter(|section_value| section_value.is_object())
+        .fold(base.clone(), |acc, section_value| merge_value(&acc, section_value))
+}
+
+pub fn resolve_locale(lat: f64, lng: f64, country: Option<&str>) -> Locale {
+    let config = &LANGUAGE_PACKS;
+    let packs = select_packs(config, lat, lng, country);
+    let patterns = merge_pack_section(config, &PATTERN_CONFIG, &packs, "patterns");
+
+    Locale { packs, patterns }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Value {
+        json!({
+            "default_packs": ["en"],
+            "countries": {
+                "ID": { "bbox": [-11.0, 95.0, 6.0, 141.0], "packs": ["id", "en"] },
+                "MY": { "bbox": [0.8, 109.5, 7.5, 119.3], "packs": ["ms", "en"] },
+                "XX": { "bbox": [0.0, 0.0, 1.0, 1.0], "packs": [] }
+            },
+            "packs": {
+                "id": { "patterns": { "market": { "name_contains": ["pasar"] } } },
+                "en": { "patterns": { "market": { "name_contains": ["market", "pasar"] } } }
+            }
+        })
+    }
+
+    #[test]
+    fn merge_value_unions_arrays_without_duplicates() {
+        let merged = merge_value(&json!(["a", "b"]), &json!(["b", "c"]));
+        assert_eq!(merged, json!(["a", "b", "c"]));
+    }
+
+    #[test]
+    fn merge_value_recurses_into_objects_and_overrides_scalars() {
+        let base = json!({ "a": { "x": 1, "list": [1] }, "b": true });
+        let overlay = json!({ "a": { "x": 2, "list": [2] }, "c": "new" });
+        assert_eq!(
+            merge_value(&base, &overlay),
+            json!({ "a": { "x": 2, "list": [1, 2] }, "b": true, "c": "new" })
+        );
+    }
+
+    #[test]
+    fn explicit_country_code_is_case_insensitive() {
+        assert_eq!(select_packs(&config(), 0.0, 0.0, Some("id")), vec!["id", "en"]);
+    }
+
+    #[test]
+    fn unknown_country_falls_back_to_coordinates() {
+        assert_eq!(select_packs(&config(), -6.2, 106.8, Some("ZZ")), vec!["id", "en"]);
+    }
+
+    #[test]
+    fn overlapping_boxes_merge_their_packs_tightest_first() {
+        // Sabah lies in both boxes; the Malaysian one is smaller.
+        assert_eq!(select_packs(&config(), 5.9, 116.0, None), vec!["ms", "en", "id"]);
+    }
+
+    #[test]
+    fn indonesian_border_areas_keep_the_indonesian_pack() {
+        // Batam, Dumai and Tarakan all fall inside the shipped Malaysian box too.
+        let config = &LANGUAGE_PACKS;
+        [(1.13, 104.05), (1.67, 101.45), (3.3, 117.6)].into_iter().for_each(|(lat, lng)| {
+            let packs = select_packs(config, lat, lng, None);
+            assert!(packs.contains(&"id".to_string()), "{:?} at {}, {}", packs, lat, lng);
+        });
+    }
+
+    #[test]
+    fn box_edges_are_inclusive() {
+        assert!(bbox_contains(&json!([0.0, 0.0, 1.0, 1.0]), 1.0, 0.0).is_some());
+        assert!(bbox_contains(&json!([0.0, 0.0, 1.0, 1.0]), 1.0001, 0.0).is_none());
+        assert!(bbox_contains(&json!([0.0, 0.0, 1.0]), 0.5, 0.5).is_none());
+    }
+
+    #[test]
+    fn empty_pack_lists_and_unmatched_points_use_default_packs() {
+        assert_eq!(select_packs(&config(), 0.5, 0.5, Some("XX")), vec!["en"]);
+        assert_eq!(select_packs(&config(), 50.0, -30.0, None), vec!["en"]);
+    }
+
+    #[test]
+    fn packs_merge_in_order_and_skip_missing_ones() {
+        let base = json!({ "market": { "name_contains": ["shop"] } });
+        let packs = vec!["id".to_string(), "missing".to_string(), "en".to_string()];
+        let merged = merge_pack_section(&config(), &base, &packs, "patterns");
+        assert_eq!(merged["market"]["name_contains"], json!(["shop", "pasar", "market"]));
+    }
+}
@@ -1,3 +1,22 @@
 pub mod overpass;
 pub mod score_calculator;
 pub mod category_detection;
+pub mod language_packs;
+pub mod query_builder;
+pub mod geo;
+pub mod geojson;
+pub mod export;
+pub mod pdf;
+pub mod report;
+pub mod spatial_index;
+pub mod heatmap;
+pub mod shared_fetch;
+pub mod area_scoring;
+pub mod walking;
+pub mod essentials;
+pub mod nearby;
+pub mod dedup;
+pub mod green_space;
+pub mod nuisance;
+pub mod night_safety;
+pub mod road_safety;
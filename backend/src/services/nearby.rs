@@ -0,0 +1,124 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use crate::models::{Facility, NearbyFacility};
+use crate::services::score_calculator::SCORING_CONFIG;
+
+const DEFAULT_TOP_N: usize = 3;
+pub const MAX_TOP_N: usize = 50;
+
+pub fn get_top_n() -> usize {
+    SCORING_CONFIG["nearby"]["top_n"]
+        .as_u64()
+        .map(|n| (n as usize).min(MAX_TOP_N))
+        .unwrap_or(DEFAULT_TOP_N)
+}
+
+// Equal distances fall back to the facility id so the order never depends on fetch order.
+pub fn cmp_by_distance(a: &Facility, b: &Facility) -> Ordering {
+    a.distance.total_cmp(&b.distance).then_with(|| a.id.cmp(&b.id))
+}
+
+pub fn sorted_by_distance(facilities: &[Facility]) -> Vec<&Facility> {
+    let mut sorted: Vec<&Facility> = facilities.iter().collect();
+    sorted.sort_by(|a, b| cmp_by_distance(a, b));
+    sorted
+}
+
+fn nearby_facility(facility: &Facility) -> NearbyFacility {
+    NearbyFacility {
+        id: facility.id.clone(),
+        osm_url: facility.osm_url.clone(),
+        name: facility.name.clone(),
+        subcategory: facility.subcategory.clone(),
+        lat: facility.lat,
+        lng: facility.lng,
+        distance: facility.distance,
+    }
+}
+
+// Closest `top_n` facilities of each category, nearest first.
+pub fn top_by_category(facilities: &[Facility], top_n: usize) -> BTreeMap<String, Vec<NearbyFacility>> {
+    sorted_by_distance(facilities)
+        .into_iter()
+        .fold(BTreeMap::<String, Vec<NearbyFacility>>::new(), |mut acc, facility| {
+            let entry = acc.entry(facility.category.clone()).or_default();
+            if entry.len() < top_n {
+                entry.push(nearby_facility(facility));
+            }
+            acc
+        })
+}
+
+pub fn nearest_by_category(facilities: &[Facility]) -> BTreeMap<String, NearbyFacility> {
+    top_by_category(facilities, 1)
+        .into_iter()
+        .filter_map(|(category, mut nearest)| nearest.pop().map(|facility| (category, facility)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn facility(id: &str, category: &str, distance: f64) -> Facility {
+        serde_json::from_value(json!({
+            "id": id,
+            "name": id,
+            "category": category,
+            "lat": 0.0,
+            "lng": 0.0,
+            "distance": distance,
+            "contribution": 1.0,
+        }))
+        .unwrap()
+    }
+
+    fn ids(list: &[NearbyFacility]) -> Vec<&str> {
+        list.iter().map(|f| f.id.as_str()).collect()
+    }
+
+    #[test]
+    fn ties_are_broken_by_id_whatever_the_input_order() {
+        let forward = vec![facility("node/2", "health", 50.0), facility("node/1", "health", 50.0)];
+        let backward: Vec<Facility> = forward.iter().rev().cloned().collect();
+        let order = |list: &[Facility]| sorted_by_distance(list).iter().map(|f| f.id.clone()).collect::<Vec<_>>();
+        assert_eq!(order(&forward), vec!["node/1", "node/2"]);
+        assert_eq!(order(&forward), order(&backward));
+    }
+
+    #[test]
+    fn top_by_category_keeps_the_closest_per_category() {
+        let facilities = vec![
+            facility("node/1", "health", 300.0),
+            facility("node/2", "health", 100.0),
+            facility("node/3", "health", 200.0),
+            facility("node/4", "market", 400.0),
+        ];
+        let top = top_by_category(&facilities, 2);
+        assert_eq!(ids(&top["health"]), vec!["node/2", "node/3"]);
+        assert_eq!(ids(&top["market"]), vec!["node/4"]);
+    }
+
+    #[test]
+    fn nearest_by_category_has_one_entry_per_category() {
+        let facilities = vec![facility("node/1", "health", 300.0), facility("node/2", "health", 100.0)];
+        let nearest = nearest_by_category(&facilities);
+        assert_eq!(nearest.len(), 1);
+        assert_eq!(nearest["health"].id, "node/2");
+    }
+
+    #[test]
+    fn empty_input_and_zero_top_n_give_no_nearest() {
+        assert!(top_by_category(&[], 3).is_empty());
+        assert!(nearest_by_category(&[]).is_empty());
+        let facilities = vec![facility("node/1", "health", 100.0)];
+        assert!(top_by_category(&facilities, 0).values().all(|list| list.is_empty()));
+    }
+
+    #[test]
+    fn configured_top_n_is_capped() {
+        assert!(get_top_n() <= MAX_TOP_N);
+    }
+}
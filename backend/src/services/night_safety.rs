@@ -0,0 +1,184 @@
+use serde_json::Value;
+
+use crate::models::{NightSafety, OverpassElement};
+use crate::services::geo::{calculate_distance, line_length_within_circle};
+use crate::services::score_calculator::SCORING_CONFIG;
+
+const DEFAULT_LIT_VALUES: &[&str] = &["yes", "24/7", "automatic", "limited", "interval"];
+const DEFAULT_LIT_WEIGHT: f64 = 60.0;
+const DEFAULT_LAMP_WEIGHT: f64 = 30.0;
+const DEFAULT_SURVEILLANCE_WEIGHT: f64 = 10.0;
+const DEFAULT_TARGET_LAMPS_PER_KM: f64 = 25.0;
+const DEFAULT_SAFETY_POINTS: f64 = 0.2;
+
+pub fn tag_is(element: &OverpassElement, key: &str, value: &str) -> bool {
+    element
+        .tags
+        .as_ref()
+        .and_then(|tags| tags.get(key))
+        .is_some_and(|v| v == value)
+}
+
+fn is_lit(element: &OverpassElement, config: &Value) -> bool {
+    let lit = match element.tags.as_ref().and_then(|tags| tags.get("lit")) {
+        Some(lit) => lit,
+        None => return false,
+    };
+    match config["lit_values"].as_array() {
+        Some(values) => values.iter().any(|v| v.as_str() == Some(lit)),
+        None => DEFAULT_LIT_VALUES.contains(&lit.as_str()),
+    }
+}
+
+// Street ways clipped to the catchment circle, with their `lit` flag.
+pub fn street_lengths(elements: &[OverpassElement], lat: f64, lng: f64, radius: f64) -> Vec<(&OverpassElement, f64)> {
+    elements
+        .iter()
+        .filter(|e| e.element_type == "way" && e.tags.as_ref().is_some_and(|tags| tags.contains_key("highway")))
+        .filter_map(|e| {
+            let line: Vec<(f64, f64)> = e.geometry.as_ref()?.iter().map(|p| (p.lat, p.lon)).collect();
+            Some((e, line_length_within_circle(&line, lat, lng, radius)))
+        })
+        .filter(|(_, length)| *length > 0.0)
+        .collect()
+}
+
+pub fn count_nodes_within(
+    elements: &[OverpassElement],
+    matches: impl Fn(&OverpassElement) -> bool,
+    lat: f64,
+    lng: f64,
+    radius: f64,
+) -> usize {
+    elements
+        .iter()
+        .filter(|e| e.element_type == "node" && matches(e))
+        .filter_map(|e| Some((e.lat?, e.lon?)))
+        .filter(|&(n_lat, n_lng)| calculate_distance(lat, lng, n_lat, n_lng) <= radius)
+        .count()
+}
+
+// Score out of 100: lit share of street length, lamp density against a target per km
+// of street, and a flat bonus when any surveillance is mapped. `safety_points` is the
+// part of it added to the safety group, where lit elements otherwise only count as
+// individual facilities.
+pub fn night_safety_summary(elements: &[OverpassElement], lat: f64, lng: f64, radius: f64) -> NightSafety {
+    let config = &SCORING_CONFIG["night_safety"];
+    let streets = street_lengths(elements, lat, lng, radius);
+    let street_length_m: f64 = streets.iter().map(|(_, length)| length).sum();
+    let lit_length_m: f64 = streets
+        .iter()
+        .filter(|(e, _)| is_lit(e, config))
+        .map(|(_, length)| length)
+        .sum();
+
+    let street_lamps = count_nodes_within(elements, |e| tag_is(e, "highway", "street_lamp"), lat, lng, radius);
+    let surveillance = count_nodes_within(elements, |e| tag_is(e, "man_made", "surveillance"), lat, lng, radius);
+
+    let street_km = street_length_m / 1000.0;
+    let lit_share = if street_length_m > 0.0 { lit_length_m / street_length_m } else { 0.0 };
+    let lamps_per_km = if street_km > 0.0 { street_lamps as f64 / street_km } else { 0.0 };
+    let target = config["target_lamps_per_km"].as_f64().unwrap_or(DEFAULT_TARGET_LAMPS_PER_KM);
+
+    let score = lit_share * config["lit_weight"].as_f64().unwrap_or(DEFAULT_LIT_WEIGHT)
+        + (lamps_per_km / target).min(1.0) * config["lamp_weight"].as_f64().unwrap_or(DEFAULT_LAMP_WEIGHT)
+        + if surveillance > 0 {
+            config["surveillance_weight"].as_f64().unwrap_or(DEFAULT_SURVEILLANCE_WEIGHT)
+        } else {
+            0.0
+        };
+
+    NightSafety {
+        street_length_m,
+        lit_length_m,
+        lit_share,
+        street_lamps,
+        lamps_per_km,
+        surveillance,
+        score,
+        safety_points: score * config["safety_points"].as_f64().unwrap_or(DEFAULT_SAFETY_POINTS),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const RADIUS: f64 = 100.0;
+    const DEGREES_PER_M: f64 = 1.0 / 111_320.0;
+
+    // An east-west street at `north` metres, running from `west` to `east` metres.
+    fn street(id: u64, north: f64, west: f64, east: f64, tags: serde_json::Value) -> OverpassElement {
+        serde_json::from_value(json!({
+            "type": "way", "id": id, "tags": tags,
+            "geometry": [
+                { "lat": north * DEGREES_PER_M, "lon": west * DEGREES_PER_M },
+                { "lat": north * DEGREES_PER_M, "lon": east * DEGREES_PER_M }
+            ]
+        }))
+        .unwrap()
+    }
+
+    fn node(id: u64, north: f64, tags: serde_json::Value) -> OverpassElement {
+        serde_json::from_value(json!({
+            "type": "node", "id": id, "lat": north * DEGREES_PER_M, "lon": 0.0, "tags": tags
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn streets_are_clipped_to_the_circle() {
+        let elements = vec![
+            street(1, 0.0, -500.0, 500.0, json!({ "highway": "residential" })),
+            street(2, 200.0, -50.0, 50.0, json!({ "highway": "residential" })),
+            street(3, 0.0, -50.0, 50.0, json!({ "waterway": "canal" })),
+        ];
+        let streets = street_lengths(&elements, 0.0, 0.0, RADIUS);
+        assert_eq!(streets.len(), 1);
+        assert!((streets[0].1 - 2.0 * RADIUS).abs() < 1.0);
+    }
+
+    #[test]
+    fn lit_share_follows_street_length() {
+        let elements = vec![
+            street(1, 0.0, -50.0, 50.0, json!({ "highway": "residential", "lit": "yes" })),
+            street(2, 10.0, -25.0, 25.0, json!({ "highway": "residential", "lit": "no" })),
+            street(3, 20.0, -25.0, 25.0, json!({ "highway": "footway" })),
+        ];
+        let summary = night_safety_summary(&elements, 0.0, 0.0, RADIUS);
+        assert!((summary.lit_share - 0.5).abs() < 0.01);
+        assert!((summary.street_length_m - 200.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn lamps_and_surveillance_are_counted_inside_the_radius() {
+        let elements = vec![
+            street(1, 0.0, -100.0, 100.0, json!({ "highway": "residential" })),
+            node(10, 10.0, json!({ "highway": "street_lamp" })),
+            node(11, 20.0, json!({ "highway": "street_lamp" })),
+            node(12, 150.0, json!({ "highway": "street_lamp" })),
+            node(13, 30.0, json!({ "man_made": "surveillance" })),
+        ];
+        let summary = night_safety_summary(&elements, 0.0, 0.0, RADIUS);
+        assert_eq!((summary.street_lamps, summary.surveillance), (2, 1));
+        assert!((summary.lamps_per_km - 10.0).abs() < 0.1);
+        assert!((summary.safety_points - summary.score * 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn lamp_density_is_capped_at_the_target() {
+        let mut elements = vec![street(1, 0.0, -50.0, 50.0, json!({ "highway": "residential" }))];
+        elements.extend((0..20).map(|i| node(10 + i, i as f64, json!({ "highway": "street_lamp" }))));
+        let summary = night_safety_summary(&elements, 0.0, 0.0, RADIUS);
+        assert!(summary.lamps_per_km > 25.0);
+        assert!((summary.score - 30.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn no_streets_scores_zero_without_dividing_by_zero() {
+        let summary = night_safety_summary(&[node(1, 0.0, json!({ "highway": "street_lamp" }))], 0.0, 0.0, RADIUS);
+        assert_eq!((summary.lit_share, summary.lamps_per_km, summary.score), (0.0, 0.0, 0.0));
+        assert_eq!(summary.street_lamps, 1);
+    }
+}
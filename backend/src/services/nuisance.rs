@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use serde_json::Value;
+
+use crate::models::{NuisancePenalty, OverpassElement};
+use crate::services::area_scoring::element_distance;
+use crate::services::query_builder::{derive_filters, geometry_query};
+use crate::services::score_calculator::{SCORE_GROUPS, SCORING_CONFIG};
+
+const DEFAULT_GROUP: &str = "environment";
+const DEFAULT_MAX_PENALTY: f64 = 10.0;
+const DEFAULT_DECAY: f64 = 1.0;
+const DEFAULT_MAX_DISTANCE: f64 = 300.0;
+
+struct NuisanceRule<'a> {
+    kind: &'a str,
+    group: &'a str,
+    max_penalty: f64,
+    decay: f64,
+    max_distance: f64,
+    // `<tag>_equals` lists, the same rule format as category_patterns.json.
+    rules: &'a Value,
+}
+
+impl NuisanceRule<'_> {
+    fn matches(&self, tags: &HashMap<String, String>) -> bool {
+        self.rules["match"].as_object().is_some_and(|rules| {
+            rules.iter().any(|(key, values)| {
+                let tag = match key.strip_suffix("_equals") {
+                    Some(tag) => tag,
+                    None => return false,
+                };
+                tags.get(tag).is_some_and(|value| {
+                    values.as_array().is_some_and(|arr| arr.iter().any(|v| v.as_str() == Some(value)))
+                })
+            })
+        })
+    }
+
+    fn penalty(&self, distance: f64) -> f64 {
+        if distance > self.max_distance {
+            return 0.0;
+        }
+        self.max_penalty * (1.0 - distance / self.max_distance).powf(self.decay)
+    }
+}
+
+// A group outside the four score groups would never reach the scores, so it falls back
+// to the default with a warning rather than being dropped silently.
+fn rule_group<'a>(kind: &str, rule: &'a Value) -> &'a str {
+    match rule["group"].as_str() {
+        None => DEFAULT_GROUP,
+        Some(group) if SCORE_GROUPS.contains(&group) => group,
+        Some(group) => {
+            eprintln!(
+                "WARNING: Nuisance '{}' has unknown group '{}', using '{}'. Valid groups: {}",
+                kind, group, DEFAULT_GROUP, SCORE_GROUPS.join(", ")
+            );
+            DEFAULT_GROUP
+        }
+    }
+}
+
+fn get_nuisance_rules(config: &Value) -> Vec<NuisanceRule<'_>> {
+    config["nuisances"]
+        .as_object()
+        .map(|nuisances| {
+            nuisances
+                .iter()
+                .map(|(kind, rule)| NuisanceRule {
+                    kind,
+                    group: rule_group(kind, rule),
+                    max_penalty: rule["max_penalty"].as_f64().unwrap_or(DEFAULT_MAX_PENALTY),
+                    decay: rule["decay_factor"].as_f64().unwrap_or(DEFAULT_DECAY),
+                    max_distance: rule["max_distance"].as_f64().unwrap_or(DEFAULT_MAX_DISTANCE),
+                    rules: rule,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Parsed once so configuration warnings are printed once, not per location.
+static NUISANCE_RULES: Lazy<Vec<NuisanceRule<'static>>> = Lazy::new(|| get_nuisance_rules(&SCORING_CONFIG));
+
+fn rules_query(rules: &[NuisanceRule], lat: f64, lng: f64) -> Option<String> {
+    let filters: Vec<(String, i32)> = rules
+        .iter()
+        .flat_map(|rule| {
+            let reach = rule.max_distance.ceil() as i32;
+            derive_filters(rule.rules, "match").into_iter().map(move |filter| (filter, reach))
+        })
+        .collect();
+    if filters.is_empty() {
+        return None;
+    }
+    Some(geometry_query(&filters, lat, lng))
+}
+
+// Each rule is fetched only as far as it can penalize.
+pub fn nuisance_query(lat: f64, lng: f64) -> Option<String> {
+    rules_query(&NUISANCE_RULES, lat, lng)
+}
+
+fn element_key(element: &OverpassElement) -> (&str, u64) {
+    (element.element_type.as_str(), element.id)
+}
+
+// Only the nearest source of each kind is penalized: a trunk road split into many
+// ways is still one road. Equally near sources resolve by id, whatever the fetch order.
+pub fn nuisance_penalties(elements: &[OverpassElement], lat: f64, lng: f64) -> Vec<NuisancePenalty> {
+    rule_penalties(&NUISANCE_RULES, elements, lat, lng)
+}
+
+fn rule_penalties(rules: &[NuisanceRule], elements: &[OverpassElement], lat: f64, lng: f64) -> Vec<NuisancePenalty> {
+    let nearest = elements
+        .iter()
+        .filter_map(|element| {
+            let tags = element.tags.as_ref()?;
+            let rule = rules.iter().find(|rule| rule.matches(tags))?;
+            let distance = element_distance(element, lat, lng)?;
+            Some((rule, element, distance))
+        })
+        .fold(HashMap::<&str, (&NuisanceRule, &OverpassElement, f64)>::new(), |mut acc, (rule, element, distance)| {
+            let closer = acc.get(rule.kind).is_none_or(|&(_, best_element, best)| {
+                distance.total_cmp(&best).then_with(|| element_key(element).cmp(&element_key(best_element))).is_lt()
+            });
+            if closer {
+                acc.insert(rule.kind, (rule, element, distance));
+            }
+            acc
+        });
+
+    let mut penalties: Vec<NuisancePenalty> = nearest
+        .into_values()
+        .map(|(rule, element, distance)| NuisancePenalty {
+            kind: rule.kind.to_string(),
+            group: rule.group.to_string(),
+            id: format!("{}/{}", element.element_type, element.id),
+            name: element.tags.as_ref().and_then(|tags| tags.get("name")).cloned(),
+            distance,
+            penalty: rule.penalty(distance),
+        })
+        .filter(|p| p.penalty > 0.0)
+        .collect();
+
+    penalties.sort_by(|a, b| b.penalty.total_cmp(&a.penalty).then_with(|| a.kind.cmp(&b.kind)));
+    penalties
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config() -> Value {
+        json!({
+            "nuisances": {
+                "railway": { "max_penalty": 8.0, "max_distance": 150.0, "match": { "railway_equals": ["rail"] } },
+                "landfill": {
+                    "group": "environment", "max_penalty": 15.0, "max_distance": 500.0,
+                    "match": { "landuse_equals": ["landfill"] }
+                },
+                "bar": { "group": "nightlife", "max_distance": 100.0, "match": { "amenity_equals": ["bar"] } }
+            }
+        })
+    }
+
+    // A node `north` metres from (0, 0).
+    fn source(element_type: &str, id: u64, north: f64, tags: Value) -> OverpassElement {
+        serde_json::from_value(json!({
+            "type": element_type, "id": id, "lat": north / 111_320.0, "lon": 0.0, "tags": tags
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn each_rule_is_fetched_within_its_own_distance() {
+        let config = config();
+        let query = rules_query(&get_nuisance_rules(&config), 1.0, 2.0).unwrap();
+        assert!(query.contains(r#"way["railway"~"^(rail)$"](around:150,1,2);"#));
+        assert!(query.contains(r#"way["landuse"~"^(landfill)$"](around:500,1,2);"#));
+        assert!(query.ends_with("out geom;"));
+    }
+
+    #[test]
+    fn unknown_groups_fall_back_to_environment() {
+        let config = config();
+        let rules = get_nuisance_rules(&config);
+        assert!(rules.iter().all(|rule| SCORE_GROUPS.contains(&rule.group)));
+        assert_eq!(rules.iter().find(|rule| rule.kind == "bar").unwrap().group, "environment");
+    }
+
+    #[test]
+    fn only_the_nearest_source_of_a_kind_counts() {
+        let config = config();
+        let rules = get_nuisance_rules(&config);
+        let elements = vec![
+            source("way", 2, 100.0, json!({ "railway": "rail" })),
+            source("way", 1, 50.0, json!({ "railway": "rail" })),
+            source("way", 3, 400.0, json!({ "landuse": "landfill" })),
+            source("way", 4, 120.0, json!({ "amenity": "bar" })),
+        ];
+        let penalties = rule_penalties(&rules, &elements, 0.0, 0.0);
+        let kinds: Vec<(&str, &str)> = penalties.iter().map(|p| (p.kind.as_str(), p.id.as_str())).collect();
+        assert_eq!(kinds, vec![("railway", "way/1"), ("landfill", "way/3")]);
+        assert!((penalties[0].penalty - 8.0 * (1.0 - 50.0 / 150.0)).abs() < 0.1);
+    }
+
+    #[test]
+    fn equally_near_sources_resolve_by_id() {
+        let config = config();
+        let rules = get_nuisance_rules(&config);
+        let a = source("way", 9, 50.0, json!({ "railway": "rail" }));
+        let b = source("node", 9, 50.0, json!({ "railway": "rail" }));
+        let forward = rule_penalties(&rules, &[a.clone(), b.clone()], 0.0, 0.0);
+        let backward = rule_penalties(&rules, &[b, a], 0.0, 0.0);
+        assert_eq!(forward[0].id, "node/9");
+        assert_eq!(backward[0].id, "node/9");
+    }
+
+    #[test]
+    fn no_rules_means_no_query_and_no_penalties() {
+        let empty = json!({});
+        let rules = get_nuisance_rules(&empty);
+        assert!(rules_query(&rules, 0.0, 0.0).is_none());
+        assert!(rule_penalties(&rules, &[], 0.0, 0.0).is_empty());
+    }
+}
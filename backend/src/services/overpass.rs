@@ -1,20 +1,66 @@
 use crate::models::{OverpassResponse, OverpassElement};
 use reqwest::Client;
+use std::collections::{HashMap, VecDeque};
 use std::error::Error;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use futures::stream::{self, StreamExt};
+use tokio::sync::Mutex;
 
 const OVERPASS_API_URL: &str = "https://overpass-api.de/api/interpreter";
 const RATE_LIMIT_DELAY_MS: u64 = 1000; 
 const MAX_CONCURRENT_REQUESTS: usize = 2;
 const MAX_RETRIES_PER_CATEGORY: u32 = 3;
 const RETRY_DELAY_MS: u64 = 2000;
+const MEMORY_CACHE_CAPACITY: usize = 256;
 
-async fn rate_limit_delay() {
-    tokio::time::sleep(std::time::Duration::from_millis(RATE_LIMIT_DELAY_MS)).await;
+#[derive(Default)]
+struct ResponseCache {
+    entries: HashMap<String, Vec<OverpassElement>>,
+    order: VecDeque<String>,
+}
+
+impl ResponseCache {
+    fn get(&self, query: &str) -> Option<Vec<OverpassElement>> {
+        self.entries.get(query).cloned()
+    }
+
+    fn insert(&mut self, query: String, elements: Vec<OverpassElement>) {
+        if self.entries.insert(query.clone(), elements).is_none() {
+            self.order.push_back(query);
+        }
+        while self.order.len() > MEMORY_CACHE_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+// Every request waits for the next free slot, so concurrent fetches from any
+// location or batch worker share one request budget against the public API.
+async fn rate_limit_delay(next_slot: &Mutex<Instant>) {
+    let mut next = next_slot.lock().await;
+    let now = Instant::now();
+    if *next > now {
+        tokio::time::sleep(*next - now).await;
+    }
+    *next = Instant::now() + Duration::from_millis(RATE_LIMIT_DELAY_MS);
+}
+
+// FNV-1a keeps cache file names stable across builds and platforms.
+fn query_fingerprint(query: &str) -> String {
+    let hash = query.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
 }
 
 async fn fetch_overpass_data_with_retry(
     client: &Client,
+    next_slot: &Mutex<Instant>,
     category: String,
     query: String,
 ) -> Result<(String, Vec<OverpassElement>), Box<dyn Error + Send + Sync>> {
@@ -26,7 +72,7 @@ async fn fetch_overpass_data_with_retry(
             tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
         }
         
-        rate_limit_delay().await;
+        rate_limit_delay(next_slot).await;
         
         let result = client
             .post(OVERPASS_API_URL)
@@ -76,12 +122,107 @@ async fn fetch_overpass_data_with_retry(
 #[derive(Clone)]
 pub struct OverpassService {
     client: Client,
+    next_slot: Arc<Mutex<Instant>>,
+    // Only batch runs keep responses in memory; the long-running server always asks
+    // Overpass so its data never goes stale.
+    cache: Option<Arc<std::sync::Mutex<ResponseCache>>>,
+    cache_dir: Option<PathBuf>,
+    // Disk entries older than this are refetched; `None` keeps them forever.
+    cache_max_age: Option<Duration>,
+    // Queries sent to Overpass rather than answered from a cache.
+    requests: Arc<AtomicUsize>,
 }
 
 impl OverpassService {
     pub fn new() -> Self {
         Self {
             client: Client::new(),
+            next_slot: Arc::new(Mutex::new(Instant::now())),
+            cache: None,
+            cache_dir: None,
+            cache_max_age: None,
+            requests: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn network_requests(&self) -> usize {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    pub fn with_memory_cache(self) -> Self {
+        Self {
+            cache: Some(Arc::new(std::sync::Mutex::new(ResponseCache::default()))),
+            ..self
+        }
+    }
+
+    pub fn with_cache_dir(self, cache_dir: PathBuf) -> Self {
+        Self {
+            cache_dir: Some(cache_dir),
+            ..self
+        }
+    }
+
+    pub fn with_cache_max_age(self, max_age: Duration) -> Self {
+        Self {
+            cache_max_age: Some(max_age),
+            ..self
+        }
+    }
+
+    fn cache_path(&self, query: &str) -> Option<PathBuf> {
+        self.cache_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.json", query_fingerprint(query))))
+    }
+
+    fn remembered(&self, query: &str) -> Option<Vec<OverpassElement>> {
+        self.cache
+            .as_ref()
+            .and_then(|cache| cache.lock().ok())
+            .and_then(|cache| cache.get(query))
+    }
+
+    async fn cached(&self, query: &str) -> Option<Vec<OverpassElement>> {
+        if let Some(elements) = self.remembered(query) {
+            return Some(elements);
+        }
+
+        let path = self.cache_path(query)?;
+        let modified = tokio::fs::metadata(&path).await.ok()?.modified().ok()?;
+        let expired = self
+            .cache_max_age
+            .is_some_and(|max_age| modified.elapsed().map_or(true, |age| age > max_age));
+        if expired {
+            return None;
+        }
+        let content = tokio::fs::read_to_string(&path).await.ok()?;
+        let elements: Vec<OverpassElement> = serde_json::from_str(&content).ok()?;
+        self.remember(query, &elements);
+        Some(elements)
+    }
+
+    fn remember(&self, query: &str, elements: &[OverpassElement]) {
+        if let Some(mut cache) = self.cache.as_ref().and_then(|cache| cache.lock().ok()) {
+            cache.insert(query.to_string(), elements.to_vec());
+        }
+    }
+
+    async fn write_cache_file(path: &std::path::Path, elements: &[OverpassElement]) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let json = serde_json::to_string(elements).map_err(std::io::Error::other)?;
+        tokio::fs::write(path, json).await
+    }
+
+    async fn store(&self, query: &str, elements: &[OverpassElement]) {
+        self.remember(query, elements);
+
+        if let Some(path) = self.cache_path(query) {
+            if let Err(e) = Self::write_cache_file(&path, elements).await {
+                eprintln!("WARNING: Failed to write cache file {}: {}", path.display(), e);
+            }
         }
     }
 
@@ -91,12 +232,25 @@ impl OverpassService {
     ) -> Result<Vec<(String, Vec<OverpassElement>)>, Box<dyn Error + Send + Sync>> {
         let facilities = stream::iter(queries)
             .map(|(category, query)| {
-                let client = self.client.clone();
+                let service = self.clone();
                 async move {
-                    fetch_overpass_data_with_retry(&client, category, query).await
+                    if let Some(elements) = service.cached(&query).await {
+                        return Ok((category, elements));
+                    }
+                    service.requests.fetch_add(1, Ordering::Relaxed);
+                    let (category, elements) = fetch_overpass_data_with_retry(
+                        &service.client,
+                        &service.next_slot,
+                        category,
+                        query.clone(),
+                    ).await?;
+                    service.store(&query, &elements).await;
+                    Ok((category, elements))
                 }
             })
-            .buffer_unordered(MAX_CONCURRENT_REQUESTS)
+            // `buffered` keeps results in query order, so downstream deduplication and
+            // indexing see the same sequence on every run.
+            .buffered(MAX_CONCURRENT_REQUESTS)
             .collect::<Vec<Result<(String, Vec<OverpassElement>), Box<dyn Error + Send + Sync>>>>()
             .await
             .into_iter()
@@ -104,3 +258,72 @@ impl OverpassService {
         Ok(facilities)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element(id: u64) -> OverpassElement {
+        serde_json::from_value(serde_json::json!({ "type": "node", "id": id, "lat": 0.0, "lon": 0.0 })).unwrap()
+    }
+
+    #[test]
+    fn memory_cache_evicts_oldest_first() {
+        let mut cache = ResponseCache::default();
+        (0..MEMORY_CACHE_CAPACITY + 1).for_each(|i| cache.insert(format!("q{}", i), vec![element(i as u64)]));
+        assert!(cache.get("q0").is_none());
+        assert_eq!(cache.get("q1").unwrap()[0].id, 1);
+        assert_eq!(cache.order.len(), MEMORY_CACHE_CAPACITY);
+    }
+
+    #[test]
+    fn reinserting_a_query_does_not_duplicate_its_slot() {
+        let mut cache = ResponseCache::default();
+        cache.insert("q".to_string(), vec![element(1)]);
+        cache.insert("q".to_string(), vec![element(2)]);
+        assert_eq!(cache.order.len(), 1);
+        assert_eq!(cache.get("q").unwrap()[0].id, 2);
+    }
+
+    #[test]
+    fn server_service_keeps_nothing_in_memory() {
+        let service = OverpassService::new();
+        service.remember("q", &[element(1)]);
+        assert!(service.remembered("q").is_none());
+
+        let batch = OverpassService::new().with_memory_cache();
+        batch.remember("q", &[element(1)]);
+        assert_eq!(batch.remembered("q").unwrap()[0].id, 1);
+    }
+
+    #[tokio::test]
+    async fn expired_disk_entries_are_ignored() {
+        let dir = std::env::temp_dir().join(format!("overpass-cache-test-{}", std::process::id()));
+        let writer = OverpassService::new().with_cache_dir(dir.clone());
+        writer.store("q", &[element(1)]).await;
+
+        let fresh = OverpassService::new().with_cache_dir(dir.clone()).with_cache_max_age(Duration::from_secs(3600));
+        assert_eq!(fresh.cached("q").await.unwrap()[0].id, 1);
+
+        let expired = OverpassService::new().with_cache_dir(dir.clone()).with_cache_max_age(Duration::ZERO);
+        assert!(expired.cached("q").await.is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn cached_queries_are_not_counted_as_requests() {
+        let batch = OverpassService::new().with_memory_cache();
+        batch.remember("q", &[element(1)]);
+        let fetched = batch.fetch_facilities(vec![("health".to_string(), "q".to_string())]).await.unwrap();
+        assert_eq!(fetched[0].1[0].id, 1);
+        assert_eq!(batch.network_requests(), 0);
+    }
+
+    #[test]
+    fn fingerprints_are_stable_and_distinct() {
+        assert_eq!(query_fingerprint(""), "cbf29ce484222325");
+        assert_eq!(query_fingerprint("a"), "af63dc4c8601ec8c");
+        assert_ne!(query_fingerprint("ab"), query_fingerprint("ba"));
+    }
+}
@@ -0,0 +1,203 @@
+pub const PAGE_WIDTH: f64 = 595.0;
+pub const PAGE_HEIGHT: f64 = 842.0;
+
+pub type Rgb = (u8, u8, u8);
+
+#[derive(Default)]
+pub struct PageBuilder {
+    ops: String,
+}
+
+fn color_components((r, g, b): Rgb) -> String {
+    format!("{:.3} {:.3} {:.3}", r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0)
+}
+
+// The standard Type1 fonts only cover WinAnsi; anything else is replaced.
+fn escape_text(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '(' | ')' | '\\' => format!("\\{}", c),
+            c if (' '..='~').contains(&c) => c.to_string(),
+            c if ('\u{a0}'..='\u{ff}').contains(&c) => format!("\\{:03o}", c as u32),
+            _ => "?".to_string(),
+        })
+        .collect()
+}
+
+impl PageBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(mut self, x: f64, y: f64, size: f64, bold: bool, text: &str) -> Self {
+        let font = if bold { "F2" } else { "F1" };
+        self.ops.push_str(&format!(
+            "BT /{} {:.1} Tf {:.2} {:.2} Td ({}) Tj ET\n",
+            font, size, x, y, escape_text(text)
+        ));
+        self
+    }
+
+    pub fn fill_rect(mut self, x: f64, y: f64, w: f64, h: f64, color: Rgb) -> Self {
+        self.ops.push_str(&format!(
+            "{} rg {:.2} {:.2} {:.2} {:.2} re f\n",
+            color_components(color), x, y, w, h
+        ));
+        self
+    }
+
+    pub fn stroke_rect(mut self, x: f64, y: f64, w: f64, h: f64, color: Rgb) -> Self {
+        self.ops.push_str(&format!(
+            "{} RG 0.5 w {:.2} {:.2} {:.2} {:.2} re S\n",
+            color_components(color), x, y, w, h
+        ));
+        self
+    }
+
+    pub fn line(mut self, from: (f64, f64), to: (f64, f64), color: Rgb) -> Self {
+        self.ops.push_str(&format!(
+            "{} RG 0.5 w {:.2} {:.2} m {:.2} {:.2} l S\n",
+            color_components(color), from.0, from.1, to.0, to.1
+        ));
+        self
+    }
+
+    // Four Bezier arcs approximate a circle well enough at map scale.
+    pub fn circle(self, cx: f64, cy: f64, r: f64, stroke: Rgb, fill: Option<Rgb>) -> Self {
+        const KAPPA: f64 = 0.552_284_8;
+        let k = r * KAPPA;
+        let mut page = self;
+        let paint = match fill {
+            Some(color) => format!("{} rg B", color_components(color)),
+            None => "S".to_string(),
+        };
+        page.ops.push_str(&format!(
+            "{} RG 0.8 w {:.2} {:.2} m \
+             {:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c \
+             {:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c \
+             {:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c \
+             {:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c h {}\n",
+            color_components(stroke),
+            cx + r, cy,
+            cx + r, cy + k, cx + k, cy + r, cx, cy + r,
+            cx - k, cy + r, cx - r, cy + k, cx - r, cy,
+            cx - r, cy - k, cx - k, cy - r, cx, cy - r,
+            cx + k, cy - r, cx + r, cy - k, cx + r, cy,
+            paint
+        ));
+        page
+    }
+
+    pub fn finish(self) -> String {
+        self.ops
+    }
+}
+
+fn push_object(buffer: &mut Vec<u8>, offsets: &mut Vec<usize>, body: &str) {
+    offsets.push(buffer.len());
+    let number = offsets.len();
+    buffer.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", number, body).as_bytes());
+}
+
+// Object layout: 1 catalog, 2 page tree, 3-4 fonts, then a (page, content) pair per page.
+pub fn render_document(pages: &[String]) -> Vec<u8> {
+    const FIRST_PAGE_OBJECT: usize = 5;
+
+    let page_refs: Vec<String> = (0..pages.len())
+        .map(|i| format!("{} 0 R", FIRST_PAGE_OBJECT + i * 2))
+        .collect();
+
+    let mut buffer: Vec<u8> = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets: Vec<usize> = Vec::new();
+
+    push_object(&mut buffer, &mut offsets, "<< /Type /Catalog /Pages 2 0 R >>");
+    push_object(
+        &mut buffer,
+        &mut offsets,
+        &format!("<< /Type /Pages /Kids [{}] /Count {} >>", page_refs.join(" "), pages.len()),
+    );
+    push_object(
+        &mut buffer,
+        &mut offsets,
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>",
+    );
+    push_object(
+        &mut buffer,
+        &mut offsets,
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>",
+    );
+
+    pages.iter().enumerate().for_each(|(i, content)| {
+        let content_object = FIRST_PAGE_OBJECT + i * 2 + 1;
+        push_object(
+            &mut buffer,
+            &mut offsets,
+            &format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH, PAGE_HEIGHT, content_object
+            ),
+        );
+        push_object(
+            &mut buffer,
+            &mut offsets,
+            &format!("<< /Length {} >>\nstream\n{}endstream", content.len(), content),
+        );
+    });
+
+    let xref_offset = buffer.len();
+    let xref_entries: String = offsets
+        .iter()
+        .map(|offset| format!("{:010} 00000 n \n", offset))
+        .collect();
+    buffer.extend_from_slice(
+        format!(
+            "xref\n0 {}\n0000000000 65535 f \n{}trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            offsets.len() + 1,
+            xref_entries,
+            offsets.len() + 1,
+            xref_offset
+        )
+        .as_bytes(),
+    );
+
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_escapes_delimiters_and_replaces_unsupported_characters() {
+        assert_eq!(escape_text(r"a(b)\c"), r"a\(b\)\\c");
+        assert_eq!(escape_text("café"), r"caf\351");
+        assert_eq!(escape_text("日本"), "??");
+        assert_eq!(escape_text(""), "");
+    }
+
+    #[test]
+    fn xref_offsets_point_at_their_objects() {
+        let pages = vec![PageBuilder::new().text(10.0, 10.0, 9.0, false, "one").finish(), String::new()];
+        let document = render_document(&pages);
+        // The binary marker in the header is not UTF-8, so offsets are read from the tail.
+        let trailer_start = document.len() - 64;
+        let tail = String::from_utf8_lossy(&document[trailer_start..]).to_string();
+        let startxref: usize = tail.rsplit("startxref\n").next().unwrap().lines().next().unwrap().parse().unwrap();
+        let xref = std::str::from_utf8(&document[startxref..]).unwrap();
+        assert!(xref.starts_with("xref\n0 9\n"));
+
+        xref.lines().skip(3).take(8).enumerate().for_each(|(i, line)| {
+            let offset: usize = line[..10].parse().unwrap();
+            assert!(document[offset..].starts_with(format!("{} 0 obj", i + 1).as_bytes()));
+        });
+        assert!(xref.contains("/Size 9"));
+    }
+
+    #[test]
+    fn empty_document_is_still_well_formed() {
+        let text = String::from_utf8_lossy(&render_document(&[])).to_string();
+        assert!(text.contains("/Kids [] /Count 0"));
+        assert!(text.ends_with("%%EOF\n"));
+    }
+}
@@ -0,0 +1,385 @@
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+
+use crate::services::category_detection::{flag_enabled, DETECTOR_FLAGS};
+
+pub static QUERY_CONFIG: Lazy<Value> = Lazy::new(|| {
+    std::fs::read_to_string("config/queries.json")
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or(json!({}))
+});
+
+const DEFAULT_ELEMENT_TYPES: &[&str] = &["node", "way"];
+
+// Detector flags that are not `<tag>_equals` lists, mapped to the Overpass filter
+// selecting exactly the elements the flag accepts.
+const FLAG_FILTERS: &[(&str, &str)] = &[
+    ("shop_non_empty", r#"["shop"]"#),
+    ("lit_yes", r#"["lit"="yes"]"#),
+    ("traffic_calming_present", r#"["traffic_calming"]"#),
+    ("tactile_paving_yes", r#"["tactile_paving"="yes"]"#),
+];
+
+fn escape_regex(value: &str) -> String {
+    value
+        .chars()
+        .flat_map(|c| match c {
+            '\\' | '.' | '*' | '+' | '?' | '(' | ')' | '[' | ']' | '{' | '}' | '|' | '^' | '$' => {
+                vec!['\\', '\\', c]
+            }
+            '"' => vec!['\\', '"'],
+            _ => vec![c],
+        })
+        .collect()
+}
+
+fn string_list(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_str())
+                .filter(|s| !s.is_empty())
+                .map(escape_regex)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn tag_filter(key: &str, values: &Value) -> Option<String> {
+    let tag = key.strip_suffix("_equals")?;
+    let alternatives = string_list(values);
+    if alternatives.is_empty() {
+        return None;
+    }
+    Some(format!(r#"["{}"~"^({})$"]"#, tag, alternatives.join("|")))
+}
+
+fn name_filter(key: &str, values: &Value) -> Option<String> {
+    let anchor = match key {
+        "name_contains" => "",
+        "name_prefixes" => "^",
+        _ => return None,
+    };
+    let alternatives = string_list(values);
+    if alternatives.is_empty() {
+        return None;
+    }
+    Some(format!(r#"["name"~"{}({})",i]"#, anchor, alternatives.join("|")))
+}
+
+fn flag_filter(flag: &str) -> Option<String> {
+    FLAG_FILTERS
+        .iter()
+        .find(|(name, _)| *name == flag)
+        .map(|(_, filter)| filter.to_string())
+}
+
+// Flags are taken from the detector's table rather than the rule keys, so a flag the
+// rule omits is fetched exactly when the classifier's default accepts it.
+pub fn derive_filters(patterns: &Value, category: &str) -> Vec<String> {
+    let rule_filters = patterns
+        .get(category)
+        .and_then(|rules| rules.as_object())
+        .map(|rules| {
+            rules
+                .iter()
+                .filter_map(|(key, value)| tag_filter(key, value).or_else(|| name_filter(key, value)))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let flag_filters = DETECTOR_FLAGS
+        .iter()
+        .filter(|(c, flag, _)| *c == category && flag_enabled(patterns, category, flag))
+        .filter_map(|(_, flag, _)| flag_filter(flag));
+
+    rule_filters.into_iter().chain(flag_filters).collect()
+}
+
+fn element_types(category: &str) -> Vec<String> {
+    QUERY_CONFIG["queries"][category]["element_types"]
+        .as_array()
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_str())
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>()
+        })
+        .filter(|types| !types.is_empty())
+        .unwrap_or_else(|| DEFAULT_ELEMENT_TYPES.iter().map(|s| s.to_string()).collect())
+}
+
+fn build_query_from_patterns(patterns: &Value, category: &str, area: &str) -> Option<String> {
+    let filters = derive_filters(patterns, category);
+    if filters.is_empty() {
+        return None;
+    }
+
+    let statements: Vec<String> = element_types(category)
+        .iter()
+        .flat_map(|element_type| {
+            filters
+                .iter()
+                .map(move |filter| format!("{}{}({});", element_type, filter, area))
+        })
+        .collect();
+
+    Some(statements.join(" "))
+}
+
+fn generate_area_query(patterns: &Value, category: &str, area: &str) -> String {
+    let query_body = build_query_from_patterns(patterns, category, area)
+        .unwrap_or_else(|| {
+            eprintln!("WARNING: Category '{}' has no derivable filters, using default query", category);
+            format!(r#"node["amenity"]({});"#, area)
+        });
+
+    format!(r#"[out:json];({});{}"#, query_body, output_statement(category))
+}
+
+// `out geom` returns full way and relation geometry so distances can be measured to the
+// nearest edge of an area; `out center` keeps responses small with one point per object.
+// Categories of large places (hospitals, campuses, parks) set their own `output_type`.
+fn output_statement(category: &str) -> &'static str {
+    let output_type = QUERY_CONFIG["queries"][category]["output_type"]
+        .as_str()
+        .or_else(|| QUERY_CONFIG["settings"]["output_type"].as_str());
+    match output_type {
+        Some("geom") => "out geom;",
+        _ => "out center;",
+    }
+}
+
+pub fn generate_overpass_query(
+    patterns: &Value,
+    category: &str,
+    lat: f64,
+    lng: f64,
+    distance: i32,
+) -> String {
+    generate_area_query(patterns, category, &format!("around:{},{},{}", distance, lat, lng))
+}
+
+// `bbox` is [min_lat, min_lng, max_lat, max_lng], which is Overpass' (south, west, north, east).
+pub fn generate_overpass_bbox_query(patterns: &Value, category: &str, bbox: [f64; 4]) -> String {
+    let [south, west, north, east] = bbox;
+    generate_area_query(patterns, category, &format!("{},{},{},{}", south, west, north, east))
+}
+
+pub fn relation_boundary_query(relation_id: u64) -> String {
+    format!("[out:json];relation({});out geom;", relation_id)
+}
+
+const DEFAULT_WALKING_HIGHWAYS: &[&str] = &[
+    "footway", "path", "pedestrian", "steps", "living_street", "residential",
+    "service", "unclassified", "tertiary", "secondary", "primary",
+];
+
+// Motorways and trunk roads are left out unless configured, so the graph does not
+// let pedestrians cross toll roads or rivers where no walkable link exists.
+pub fn walking_network_query(lat: f64, lng: f64, distance: i32) -> String {
+    let config = &QUERY_CONFIG["walking_network"];
+    let highways = Some(string_list(&config["highway"]))
+        .filter(|list| !list.is_empty())
+        .unwrap_or_else(|| DEFAULT_WALKING_HIGHWAYS.iter().map(|s| s.to_string()).collect());
+    let excluded = string_list(&config["excluded_access"]);
+
+    let access_filters: String = if excluded.is_empty() {
+        String::new()
+    } else {
+        ["foot", "access"]
+            .iter()
+            .map(|tag| format!(r#"["{}"!~"^({})$"]"#, tag, excluded.join("|")))
+            .collect()
+    };
+
+    format!(
+        r#"[out:json];way["highway"~"^({})$"]{}(around:{},{},{});out geom;"#,
+        highways.join("|"), access_filters, distance, lat, lng
+    )
+}
+
+const DEFAULT_GREEN_AREA_TAGS: &[(&str, &[&str])] = &[
+    ("leisure", &["park", "garden"]),
+    ("landuse", &["grass", "forest", "meadow"]),
+    ("natural", &["wood"]),
+];
+
+// Green areas need their outlines to measure area, so this query always uses `out geom`.
+pub fn green_space_query(lat: f64, lng: f64, distance: i32) -> String {
+    let config = &QUERY_CONFIG["green_space"];
+    let area = format!("around:{},{},{}", distance, lat, lng);
+
+    let area_filters: Vec<String> = config["area_tags"]
+        .as_object()
+        .map(|tags| {
+            tags.iter()
+                .map(|(key, values)| (key.clone(), string_list(values)))
+                .filter(|(_, values)| !values.is_empty())
+                .map(|(key, values)| format!(r#"["{}"~"^({})$"]"#, key, values.join("|")))
+                .collect::<Vec<_>>()
+        })
+        .filter(|filters| !filters.is_empty())
+        .unwrap_or_else(|| {
+            DEFAULT_GREEN_AREA_TAGS
+                .iter()
+                .map(|(key, values)| format!(r#"["{}"~"^({})$"]"#, key, values.join("|")))
+                .collect()
+        });
+
+    let tree_filter = match config["tree_tag"].as_str().and_then(|tag| tag.split_once('=')) {
+        Some((key, value)) => format!(r#"["{}"="{}"]"#, key, value),
+        None => r#"["natural"="tree"]"#.to_string(),
+    };
+
+    let statements: String = area_filters
+        .iter()
+        .flat_map(|filter| ["way", "relation"].map(|element_type| format!("{}{}({});", element_type, filter, area)))
+        .chain(std::iter::once(format!("node{}({});", tree_filter, area)))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    format!("[out:json];({});out geom;", statements)
+}
+
+// Every element type matching each filter within that filter's own distance, with full
+// geometry, for analyses that measure distances to lines and areas rather than to
+// facility centers.
+pub fn geometry_query(filters: &[(String, i32)], lat: f64, lng: f64) -> String {
+    let statements: Vec<String> = filters
+        .iter()
+        .flat_map(|(filter, distance)| {
+            let area = format!("around:{},{},{}", distance, lat, lng);
+            ["node", "way", "relation"].map(|element_type| format!("{}{}({});", element_type, filter, area))
+        })
+        .collect();
+
+    format!("[out:json];({});out geom;", statements.join(" "))
+}
+
+const DEFAULT_STREET_HIGHWAYS: &[&str] = &[
+    "trunk", "trunk_link", "primary", "secondary", "tertiary", "unclassified", "residential",
+    "living_street", "service", "pedestrian", "footway", "path",
+];
+
+fn feature_filter(feature: &str) -> String {
+    match feature.split_once('=') {
+        Some((key, value)) => format!(r#"["{}"="{}"]"#, key, value),
+        None => format!(r#"["{}"]"#, feature),
+    }
+}
+
+// Street ways with geometry plus the point features (lamps, cameras, crossings) that
+// street-level analyses measure along them.
+pub fn street_query(lat: f64, lng: f64, distance: i32) -> String {
+    let config = &QUERY_CONFIG["streets"];
+    let area = format!("around:{},{},{}", distance, lat, lng);
+    let highways = Some(string_list(&config["highway"]))
+        .filter(|list| !list.is_empty())
+        .unwrap_or_else(|| DEFAULT_STREET_HIGHWAYS.iter().map(|s| s.to_string()).collect());
+
+    let features: Vec<String> = config["point_features"]
+        .as_array()
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_str())
+                .map(|feature| format!("node{}({});", feature_filter(feature), area))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    format!(
+        r#"[out:json];(way["highway"~"^({})$"]({}); {});out geom;"#,
+        highways.join("|"), area, features.join(" ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_regex_escapes_metacharacters_and_quotes() {
+        assert_eq!(escape_regex("a.b"), r"a\\.b");
+        assert_eq!(escape_regex("(x|y)"), r"\\(x\\|y\\)");
+        assert_eq!(escape_regex(r#"say "hi""#), r#"say \"hi\""#);
+        assert_eq!(escape_regex("plain text"), "plain text");
+        assert_eq!(escape_regex(""), "");
+    }
+
+    #[test]
+    fn derive_filters_maps_each_rule_kind() {
+        let patterns = json!({
+            "market": {
+                "amenity_equals": ["marketplace", "food_court"],
+                "name_contains": ["pasar"],
+                "name_prefixes": ["toko "],
+                "shop_non_empty": true,
+                "lit_yes": false,
+                "unknown_rule": ["x"]
+            }
+        });
+        let mut filters = derive_filters(&patterns, "market");
+        filters.sort();
+        assert_eq!(
+            filters,
+            vec![
+                r#"["amenity"~"^(marketplace|food_court)$"]"#,
+                r#"["name"~"(pasar)",i]"#,
+                r#"["name"~"^(toko )",i]"#,
+                r#"["shop"]"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn omitted_flags_follow_the_classifier_default() {
+        let omitted = json!({ "safety": { "highway_equals": ["street_lamp"] } });
+        let disabled = json!({ "safety": { "lit_yes": false, "traffic_calming_present": false } });
+        let filters = derive_filters(&omitted, "safety");
+        assert!(filters.contains(&r#"["lit"="yes"]"#.to_string()));
+        assert!(filters.contains(&r#"["traffic_calming"]"#.to_string()));
+        assert!(flag_enabled(&omitted, "safety", "lit_yes"));
+        assert!(derive_filters(&disabled, "safety").is_empty());
+        assert!(!flag_enabled(&disabled, "safety", "lit_yes"));
+    }
+
+    #[test]
+    fn empty_lists_and_missing_categories_derive_nothing() {
+        let patterns = json!({ "health": { "amenity_equals": [], "name_contains": [""] } });
+        assert!(derive_filters(&patterns, "health").is_empty());
+        assert!(derive_filters(&patterns, "police").is_empty());
+    }
+
+    #[test]
+    fn categories_without_filters_fall_back_to_any_amenity() {
+        let query = generate_overpass_query(&json!({}), "police", -6.2, 106.8, 500);
+        assert!(query.contains(r#"node["amenity"](around:500,-6.2,106.8);"#));
+    }
+
+    #[test]
+    fn bbox_queries_use_south_west_north_east() {
+        let patterns = json!({ "police": { "amenity_equals": ["police"] } });
+        let query = generate_overpass_bbox_query(&patterns, "police", [-6.3, 106.7, -6.1, 106.9]);
+        assert!(query.contains(r#"["amenity"~"^(police)$"](-6.3,106.7,-6.1,106.9);"#));
+    }
+
+    #[test]
+    fn area_categories_ask_for_full_geometry() {
+        assert_eq!(output_statement("recreation"), "out geom;");
+        assert_eq!(output_statement("health"), "out geom;");
+        assert_eq!(output_statement("transport"), "out center;");
+        assert_eq!(output_statement("unknown"), "out center;");
+    }
+
+    #[test]
+    fn street_query_includes_trunk_roads_and_point_features() {
+        let query = street_query(1.0, 2.0, 300);
+        assert!(query.contains(r#"way["highway"~"^(trunk|trunk_link|"#));
+        assert!(query.contains(r#"node["highway"="street_lamp"](around:300,1,2);"#));
+        assert!(query.contains(r#"node["traffic_calming"](around:300,1,2);"#));
+        assert!(query.ends_with("out geom;"));
+    }
+}
@@ -0,0 +1,523 @@
+use std::collections::BTreeMap;
+
+use crate::models::{DistanceMode, Facility, LocationData};
+use crate::services::nearby::sorted_by_distance;
+use crate::services::pdf::{render_document, PageBuilder, Rgb, PAGE_HEIGHT, PAGE_WIDTH};
+use crate::SEARCH_RADIUS;
+
+const MAP_SIZE: f64 = 240.0;
+const METERS_PER_DEGREE: f64 = 111_320.0;
+const PDF_FACILITY_ROWS_FIRST_PAGE: usize = 28;
+const PDF_FACILITY_ROWS_PER_PAGE: usize = 48;
+
+const CATEGORY_COLORS: &[(&str, Rgb)] = &[
+    ("health", (220, 38, 38)),
+    ("education", (37, 99, 235)),
+    ("market", (234, 88, 12)),
+    ("transport", (124, 58, 237)),
+    ("walkability", (13, 148, 136)),
+    ("recreation", (22, 163, 74)),
+    ("safety", (202, 138, 4)),
+    ("police", (30, 64, 175)),
+    ("religious", (190, 24, 93)),
+    ("accessibility", (8, 145, 178)),
+];
+const DEFAULT_COLOR: Rgb = (100, 116, 139);
+const ORIGIN_COLOR: Rgb = (15, 23, 42);
+const GRID_COLOR: Rgb = (203, 213, 225);
+
+struct CategoryBreakdown {
+    category: String,
+    count: usize,
+    contribution: f64,
+    subcategories: BTreeMap<String, usize>,
+}
+
+fn category_color(category: &str) -> Rgb {
+    CATEGORY_COLORS
+        .iter()
+        .find(|(name, _)| *name == category)
+        .map(|(_, color)| *color)
+        .unwrap_or(DEFAULT_COLOR)
+}
+
+fn hex_color((r, g, b): Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn category_breakdown(location: &LocationData) -> Vec<CategoryBreakdown> {
+    location
+        .facilities
+        .iter()
+        .fold(BTreeMap::new(), |mut acc: BTreeMap<String, CategoryBreakdown>, f| {
+            let entry = acc.entry(f.category.clone()).or_insert_with(|| CategoryBreakdown {
+                category: f.category.clone(),
+                count: 0,
+                contribution: 0.0,
+                subcategories: BTreeMap::new(),
+            });
+            entry.count += 1;
+            entry.contribution += f.contribution;
+            if let Some(sub) = &f.subcategory {
+                *entry.subcategories.entry(sub.clone()).or_insert(0) += 1;
+            }
+            acc
+        })
+        .into_values()
+        .collect()
+}
+
+fn subcategory_summary(subcategories: &BTreeMap<String, usize>) -> String {
+    subcategories
+        .iter()
+        .map(|(name, count)| format!("{} {}", name, count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn score_rows(location: &LocationData) -> [(&'static str, f64); 5] {
+    let s = &location.scores;
+    [
+        ("Overall", s.overall),
+        ("Services", s.services),
+        ("Mobility", s.mobility),
+        ("Safety", s.safety),
+        ("Environment", s.environment),
+    ]
+}
+
+fn offset_m(location: &LocationData, lat: f64, lng: f64) -> (f64, f64) {
+    let dx = (lng - location.lng) * METERS_PER_DEGREE * location.lat.to_radians().cos();
+    let dy = (lat - location.lat) * METERS_PER_DEGREE;
+    (dx, dy)
+}
+
+// Results saved before catchments were recorded were scored with the default radius.
+fn catchment_radius(location: &LocationData) -> f64 {
+    location
+        .catchment
+        .as_ref()
+        .map(|catchment| catchment.radius_m)
+        .unwrap_or(SEARCH_RADIUS as f64)
+}
+
+// How far from the center the map must reach: the catchment around a point, or the
+// farthest boundary vertex plus the catchment around an area.
+fn map_extent(location: &LocationData) -> f64 {
+    let boundary_reach = location
+        .area
+        .iter()
+        .flat_map(|area| area.boundary.iter())
+        .map(|&[lng, lat]| {
+            let (dx, dy) = offset_m(location, lat, lng);
+            (dx * dx + dy * dy).sqrt()
+        })
+        .fold(0.0, f64::max);
+    boundary_reach + catchment_radius(location)
+}
+
+// Local equirectangular projection centered on the analyzed point, scaled so the
+// map extent fills the map square. Returns offsets in map units from the center.
+fn project(location: &LocationData, lat: f64, lng: f64, extent: f64) -> (f64, f64) {
+    let scale = (MAP_SIZE / 2.0) / extent;
+    let (dx, dy) = offset_m(location, lat, lng);
+    (dx * scale, dy * scale)
+}
+
+fn catchment_label(location: &LocationData) -> String {
+    let radius = catchment_radius(location);
+    let walking = location
+        .catchment
+        .as_ref()
+        .is_some_and(|catchment| catchment.distance_mode == DistanceMode::Walking);
+    match (&location.area, walking) {
+        (Some(_), _) => format!("Area boundary and {:.0} m around it", radius),
+        (None, true) => format!("Walking distance {:.0} m", radius),
+        (None, false) => format!("Search radius {:.0} m", radius),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            '"' => "&quot;".to_string(),
+            '\'' => "&#39;".to_string(),
+            _ => c.to_string(),
+        })
+        .collect()
+}
+
+fn facility_link(facility: &Facility) -> String {
+    if facility.osm_url.is_empty() {
+        return escape_html(&facility.name);
+    }
+    format!(r#"<a href="{}">{}</a>"#, escape_html(&facility.osm_url), escape_html(&facility.name))
+}
+
+fn svg_map(location: &LocationData) -> String {
+    let half = MAP_SIZE / 2.0;
+    let extent = map_extent(location);
+    let outline = match &location.area {
+        Some(area) => {
+            let points: Vec<String> = area
+                .boundary
+                .iter()
+                .map(|&[lng, lat]| {
+                    let (x, y) = project(location, lat, lng, extent);
+                    format!("{:.1},{:.1}", half + x, half - y)
+                })
+                .collect();
+            format!(r#"<polygon points="{}" fill="none" stroke="{}" stroke-dasharray="4 3"/>"#,
+                points.join(" "), hex_color(ORIGIN_COLOR))
+        }
+        None => format!(r#"<circle cx="{half}" cy="{half}" r="{half}" fill="none" stroke="{}" stroke-dasharray="4 3"/>"#,
+            hex_color(ORIGIN_COLOR)),
+    };
+    let markers: String = location
+        .facilities
+        .iter()
+        .map(|f| {
+            let (x, y) = project(location, f.lat, f.lng, extent);
+            format!(
+                r#"<circle cx="{:.1}" cy="{:.1}" r="3" fill="{}"><title>{}</title></circle>"#,
+                half + x,
+                half - y,
+                hex_color(category_color(&f.category)),
+                escape_html(&f.name)
+            )
+        })
+        .collect();
+
+    format!(
+        r##"<svg class="map" viewBox="-10 -10 {size} {size}" width="{size}" height="{size}" xmlns="http://www.w3.org/2000/svg">
+<rect x="-10" y="-10" width="{size}" height="{size}" fill="#f8fafc"/>
+<line x1="{half}" y1="0" x2="{half}" y2="{full}" stroke="{grid}"/>
+<line x1="0" y1="{half}" x2="{full}" y2="{half}" stroke="{grid}"/>
+{outline}
+{markers}
+<circle cx="{half}" cy="{half}" r="5" fill="{origin}"/>
+</svg>"##,
+        size = MAP_SIZE + 20.0,
+        half = half,
+        full = MAP_SIZE,
+        grid = hex_color(GRID_COLOR),
+        origin = hex_color(ORIGIN_COLOR),
+        outline = outline,
+        markers = markers,
+    )
+}
+
+fn html_location_section(index: usize, location: &LocationData) -> String {
+    let scores: String = score_rows(location)
+        .iter()
+        .map(|(label, value)| format!("<tr><th>{}</th><td>{:.1}</td></tr>", label, value))
+        .collect();
+
+    let breakdown: String = category_breakdown(location)
+        .iter()
+        .map(|b| {
+            format!(
+                r#"<tr><td><span class="swatch" style="background:{}"></span>{}</td><td>{}</td><td>{:.1}</td><td>{}</td></tr>"#,
+                hex_color(category_color(&b.category)),
+                escape_html(&b.category),
+                b.count,
+                b.contribution,
+                escape_html(&subcategory_summary(&b.subcategories))
+            )
+        })
+        .collect();
+
+    let facilities: String = sorted_by_distance(&location.facilities)
+        .iter()
+        .map(|f| {
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{:.0} m</td><td>{:.2}</td></tr>",
+                facility_link(f),
+                escape_html(&f.category),
+                escape_html(f.subcategory.as_deref().unwrap_or("")),
+                f.distance,
+                f.contribution
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<section>
+<h2>Location {index}: {address}</h2>
+<div class="summary">
+<table class="scores"><tbody>{scores}</tbody></table>
+{map}
+</div>
+<h3>Category breakdown</h3>
+<table><thead><tr><th>Category</th><th>Facilities</th><th>Contribution</th><th>Subcategories</th></tr></thead><tbody>{breakdown}</tbody></table>
+<h3>Facilities ({catchment})</h3>
+<table><thead><tr><th>Name</th><th>Category</th><th>Subcategory</th><th>Distance</th><th>Contribution</th></tr></thead><tbody>{facilities}</tbody></table>
+</section>"#,
+        index = index + 1,
+        address = escape_html(&location.address),
+        scores = scores,
+        map = svg_map(location),
+        breakdown = breakdown,
+        catchment = escape_html(&catchment_label(location)),
+        facilities = facilities,
+    )
+}
+
+pub fn render_html(locations: &[LocationData]) -> String {
+    let comparison: String = locations
+        .iter()
+        .enumerate()
+        .map(|(i, location)| {
+            let cells: String = score_rows(location)
+                .iter()
+                .map(|(_, value)| format!("<td>{:.1}</td>", value))
+                .collect();
+            format!("<tr><th>{}. {}</th>{}</tr>", i + 1, escape_html(&location.address), cells)
+        })
+        .collect();
+
+    let sections: String = locations
+        .iter()
+        .enumerate()
+        .map(|(i, location)| html_location_section(i, location))
+        .collect();
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Futuricty Livability Report</title>
+<style>
+body {{ font-family: Helvetica, Arial, sans-serif; color: #0f172a; margin: 2rem; }}
+table {{ border-collapse: collapse; margin: 0.5rem 0 1rem; }}
+th, td {{ border: 1px solid #cbd5e1; padding: 0.25rem 0.6rem; text-align: left; font-size: 0.9rem; }}
+.summary {{ display: flex; gap: 2rem; align-items: flex-start; }}
+.swatch {{ display: inline-block; width: 0.7rem; height: 0.7rem; margin-right: 0.4rem; border-radius: 50%; }}
+section {{ page-break-before: always; }}
+</style>
+</head>
+<body>
+<h1>Futuricty Livability Report</h1>
+<table><thead><tr><th>Location</th><th>Overall</th><th>Services</th><th>Mobility</th><th>Safety</th><th>Environment</th></tr></thead><tbody>{comparison}</tbody></table>
+{sections}
+</body>
+</html>
+"#,
+        comparison = comparison,
+        sections = sections,
+    )
+}
+
+fn pdf_map(page: PageBuilder, location: &LocationData, left: f64, bottom: f64) -> PageBuilder {
+    let half = MAP_SIZE / 2.0;
+    let (cx, cy) = (left + half, bottom + half);
+    let extent = map_extent(location);
+
+    let framed = page
+        .fill_rect(left, bottom, MAP_SIZE, MAP_SIZE, (248, 250, 252))
+        .stroke_rect(left, bottom, MAP_SIZE, MAP_SIZE, GRID_COLOR)
+        .line((cx, bottom), (cx, bottom + MAP_SIZE), GRID_COLOR)
+        .line((left, cy), (left + MAP_SIZE, cy), GRID_COLOR);
+
+    let outlined = match &location.area {
+        Some(area) => {
+            let points: Vec<(f64, f64)> = area
+                .boundary
+                .iter()
+                .map(|&[lng, lat]| {
+                    let (x, y) = project(location, lat, lng, extent);
+                    (cx + x, cy + y)
+                })
+                .collect();
+            points.windows(2).fold(framed, |page, w| page.line(w[0], w[1], ORIGIN_COLOR))
+        }
+        None => framed.circle(cx, cy, half, ORIGIN_COLOR, None),
+    };
+
+    location
+        .facilities
+        .iter()
+        .fold(outlined, |page, f| {
+            let (x, y) = project(location, f.lat, f.lng, extent);
+            let color = category_color(&f.category);
+            page.circle(cx + x, cy + y, 2.5, color, Some(color))
+        })
+        .circle(cx, cy, 4.0, ORIGIN_COLOR, Some(ORIGIN_COLOR))
+}
+
+fn pdf_facility_rows(page: PageBuilder, facilities: &[&Facility], top: f64) -> PageBuilder {
+    const ROW_HEIGHT: f64 = 14.0;
+    let header = page
+        .text(40.0, top, 9.0, true, "Name")
+        .text(260.0, top, 9.0, true, "Category")
+        .text(350.0, top, 9.0, true, "Subcategory")
+        .text(440.0, top, 9.0, true, "Distance")
+        .text(500.0, top, 9.0, true, "Contribution");
+
+    facilities.iter().enumerate().fold(header, |page, (i, f)| {
+        let y = top - ROW_HEIGHT * (i as f64 + 1.0);
+        let name: String = f.name.chars().take(42).collect();
+        page.text(40.0, y, 8.5, false, &name)
+            .text(260.0, y, 8.5, false, &f.category)
+            .text(350.0, y, 8.5, false, f.subcategory.as_deref().unwrap_or("-"))
+            .text(440.0, y, 8.5, false, &format!("{:.0} m", f.distance))
+            .text(500.0, y, 8.5, false, &format!("{:.2}", f.contribution))
+    })
+}
+
+fn pdf_location_pages(index: usize, location: &LocationData) -> Vec<String> {
+    let top = PAGE_HEIGHT - 50.0;
+    let title = format!("Location {}: {}", index + 1, location.address);
+
+    let with_scores = score_rows(location).iter().enumerate().fold(
+        PageBuilder::new().text(40.0, top, 16.0, true, &title),
+        |page, (i, (label, value))| {
+            let y = top - 30.0 - 18.0 * i as f64;
+            let bar_width = 150.0 * (value / 100.0).clamp(0.0, 1.0);
+            page.text(40.0, y, 10.0, i == 0, label)
+                .fill_rect(120.0, y - 2.0, 150.0, 10.0, (226, 232, 240))
+                .fill_rect(120.0, y - 2.0, bar_width, 10.0, (37, 99, 235))
+                .text(280.0, y, 10.0, false, &format!("{:.1}", value))
+        },
+    );
+
+    let breakdown_top = top - 140.0;
+    let with_breakdown = category_breakdown(location).iter().enumerate().fold(
+        with_scores
+            .text(40.0, breakdown_top, 11.0, true, "Category breakdown")
+            .text(40.0, breakdown_top - 16.0, 9.0, true, "Category")
+            .text(130.0, breakdown_top - 16.0, 9.0, true, "Count")
+            .text(175.0, breakdown_top - 16.0, 9.0, true, "Contribution"),
+        |page, (i, b)| {
+            let y = breakdown_top - 30.0 - 13.0 * i as f64;
+            let color = category_color(&b.category);
+            page.fill_rect(40.0, y, 7.0, 7.0, color)
+                .text(52.0, y, 8.5, false, &b.category)
+                .text(130.0, y, 8.5, false, &b.count.to_string())
+                .text(175.0, y, 8.5, false, &format!("{:.1}", b.contribution))
+        },
+    );
+
+    let map_bottom = top - 30.0 - MAP_SIZE;
+    let with_map = pdf_map(with_breakdown, location, PAGE_WIDTH - 40.0 - MAP_SIZE, map_bottom)
+        .text(PAGE_WIDTH - 40.0 - MAP_SIZE, map_bottom - 14.0, 8.0, false,
+              &catchment_label(location));
+
+    let facilities = sorted_by_distance(&location.facilities);
+    let (first, rest) = facilities.split_at(facilities.len().min(PDF_FACILITY_ROWS_FIRST_PAGE));
+    let list_top = top - 330.0;
+
+    let first_page = pdf_facility_rows(
+        with_map.text(40.0, list_top + 20.0, 11.0, true, "Facilities by distance"),
+        first,
+        list_top,
+    )
+    .finish();
+
+    let continuation_pages = rest.chunks(PDF_FACILITY_ROWS_PER_PAGE).map(|chunk| {
+        pdf_facility_rows(
+            PageBuilder::new().text(40.0, top, 11.0, true, &format!("{} (continued)", title)),
+            chunk,
+            top - 25.0,
+        )
+        .finish()
+    });
+
+    std::iter::once(first_page).chain(continuation_pages).collect()
+}
+
+pub fn render_pdf(locations: &[LocationData]) -> Vec<u8> {
+    let pages: Vec<String> = locations
+        .iter()
+        .enumerate()
+        .flat_map(|(i, location)| pdf_location_pages(i, location))
+        .collect();
+
+    render_document(&pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn location(area: Option<serde_json::Value>, catchment: serde_json::Value) -> LocationData {
+        serde_json::from_value(json!({
+            "address": "Jl. <Test> & Co", "lat": 0.0, "lng": 0.0,
+            "facility_counts": {
+                "health": 1, "education": 0, "market": 0, "transport": 0, "walkability": 0,
+                "recreation": 0, "safety": 0, "police": 0, "religious": 0, "accessibility": 0
+            },
+            "subcategory_counts": {},
+            "scores": { "overall": 50.0, "services": 0.0, "mobility": 0.0, "safety": 0.0, "environment": 0.0 },
+            "nearby_facilities": [],
+            "facilities": [{
+                "id": "node/1", "name": "Klinik", "category": "health", "lat": 0.001, "lng": 0.0,
+                "distance": 111.0, "contribution": 5.0
+            }],
+            "area": area,
+            "catchment": catchment,
+        }))
+        .unwrap()
+    }
+
+    fn square_area() -> serde_json::Value {
+        json!({
+            "area_m2": 0.0, "perimeter_m": 0.0, "facilities_inside": 0, "density_per_km2": {},
+            "boundary": [[-0.01, -0.01], [0.01, -0.01], [0.01, 0.01], [-0.01, 0.01], [-0.01, -0.01]]
+        })
+    }
+
+    fn catchment(radius_m: f64, distance_mode: &str) -> serde_json::Value {
+        json!({ "radius_m": radius_m, "distance_mode": distance_mode })
+    }
+
+    #[test]
+    fn point_maps_span_the_recorded_catchment() {
+        let point = location(None, catchment(400.0, "straight"));
+        assert_eq!(map_extent(&point), 400.0);
+        assert!(svg_map(&point).contains(r#"r="120""#));
+        assert_eq!(catchment_label(&point), "Search radius 400 m");
+    }
+
+    #[test]
+    fn walking_catchments_are_labelled_as_walking_distance() {
+        let point = location(None, catchment(800.0, "walking"));
+        assert_eq!(map_extent(&point), 800.0);
+        assert_eq!(catchment_label(&point), "Walking distance 800 m");
+    }
+
+    #[test]
+    fn results_without_a_catchment_use_the_default_radius() {
+        let point = location(None, serde_json::Value::Null);
+        assert_eq!(map_extent(&point), SEARCH_RADIUS as f64);
+    }
+
+    #[test]
+    fn area_maps_span_the_boundary_plus_catchment() {
+        let area = location(Some(square_area()), catchment(500.0, "straight"));
+        let corner = (0.01f64.powi(2) * 2.0).sqrt() * METERS_PER_DEGREE;
+        assert!((map_extent(&area) - (corner + 500.0)).abs() < 1.0);
+        assert!(svg_map(&area).contains("<polygon"));
+        assert_eq!(catchment_label(&area), "Area boundary and 500 m around it");
+    }
+
+    #[test]
+    fn html_escapes_user_text() {
+        let html = render_html(&[location(None, serde_json::Value::Null)]);
+        assert!(html.contains("Jl. &lt;Test&gt; &amp; Co"));
+        assert!(!html.contains("<Test>"));
+    }
+
+    #[test]
+    fn long_facility_lists_continue_on_new_pages() {
+        let mut crowded = location(None, serde_json::Value::Null);
+        crowded.facilities = vec![crowded.facilities[0].clone(); PDF_FACILITY_ROWS_FIRST_PAGE + 1];
+        assert_eq!(pdf_location_pages(0, &crowded).len(), 2);
+        assert_eq!(pdf_location_pages(0, &location(None, serde_json::Value::Null)).len(), 1);
+    }
+}
@@ -0,0 +1,193 @@
+use serde_json::Value;
+
+use crate::models::{OverpassElement, RoadSafety};
+use crate::services::night_safety::{count_nodes_within, street_lengths, tag_is};
+use crate::services::score_calculator::SCORING_CONFIG;
+
+const DEFAULT_VEHICLE_HIGHWAYS: &[&str] = &[
+    "trunk", "trunk_link", "primary", "secondary", "tertiary", "unclassified", "residential",
+    "living_street", "service",
+];
+const DEFAULT_SPEED_LIMIT_KMH: f64 = 50.0;
+const KMH_PER_MPH: f64 = 1.609344;
+
+fn is_vehicle_road(element: &OverpassElement, config: &Value) -> bool {
+    let highway = match element.tags.as_ref().and_then(|tags| tags.get("highway")) {
+        Some(highway) => highway,
+        None => return false,
+    };
+    match config["vehicle_highways"].as_array() {
+        Some(values) => values.iter().any(|v| v.as_str() == Some(highway)),
+        None => DEFAULT_VEHICLE_HIGHWAYS.contains(&highway.as_str()),
+    }
+}
+
+// Numeric limits only ("50", "50 km/h", "30mph"); zone codes such as "DE:urban" are
+// left unknown.
+fn maxspeed_kmh(element: &OverpassElement) -> Option<f64> {
+    let raw = element.tags.as_ref()?.get("maxspeed")?.trim();
+    let split = raw.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(raw.len());
+    let (number, unit) = raw.split_at(split);
+    let speed: f64 = number.parse().ok()?;
+    match unit.trim() {
+        "" | "km/h" | "kmh" => Some(speed),
+        "mph" => Some(speed * KMH_PER_MPH),
+        _ => None,
+    }
+}
+
+fn is_sidewalk(value: Option<&String>) -> bool {
+    value.is_some_and(|v| v == "yes" || v == "separate")
+}
+
+// Fraction of the road's two sides with a mapped sidewalk.
+fn sidewalk_coverage(element: &OverpassElement) -> f64 {
+    let tags = match element.tags.as_ref() {
+        Some(tags) => tags,
+        None => return 0.0,
+    };
+    match tags.get("sidewalk").map(String::as_str) {
+        Some("both") | Some("yes") | Some("separate") => return 1.0,
+        Some("left") | Some("right") => return 0.5,
+        _ => {}
+    }
+    if is_sidewalk(tags.get("sidewalk:both")) {
+        return 1.0;
+    }
+    [tags.get("sidewalk:left"), tags.get("sidewalk:right")]
+        .into_iter()
+        .filter(|side| is_sidewalk(*side))
+        .count() as f64
+        * 0.5
+}
+
+fn length_share(roads: &[(&OverpassElement, f64)], weight: impl Fn(&OverpassElement) -> f64) -> f64 {
+    let total: f64 = roads.iter().map(|(_, length)| length).sum();
+    if total <= 0.0 {
+        return 0.0;
+    }
+    roads.iter().map(|(e, length)| weight(e) * length).sum::<f64>() / total
+}
+
+// Shares are weighted by road length inside the catchment; footways and paths are
+// excluded so they neither dilute the speed share nor count as roads lacking sidewalks.
+// The fast share covers only roads with a known limit; the rest is `unknown_speed_share`.
+pub fn road_safety_summary(elements: &[OverpassElement], lat: f64, lng: f64, radius: f64) -> RoadSafety {
+    let config = &SCORING_CONFIG["road_safety"];
+    let speed_limit = config["speed_limit_kmh"].as_f64().unwrap_or(DEFAULT_SPEED_LIMIT_KMH);
+    let roads: Vec<(&OverpassElement, f64)> = street_lengths(elements, lat, lng, radius)
+        .into_iter()
+        .filter(|(e, _)| is_vehicle_road(e, config))
+        .collect();
+
+    let road_length_m: f64 = roads.iter().map(|(_, length)| length).sum();
+    let signed: Vec<(&OverpassElement, f64)> = roads
+        .iter()
+        .filter(|(e, _)| maxspeed_kmh(e).is_some())
+        .copied()
+        .collect();
+
+    let crossings = count_nodes_within(elements, |e| tag_is(e, "highway", "crossing"), lat, lng, radius);
+    let traffic_calming = count_nodes_within(
+        elements,
+        |e| e.tags.as_ref().is_some_and(|tags| tags.contains_key("traffic_calming")),
+        lat,
+        lng,
+        radius,
+    );
+    let road_km = road_length_m / 1000.0;
+
+    RoadSafety {
+        road_length_m,
+        crossings,
+        crossings_per_km: if road_km > 0.0 { crossings as f64 / road_km } else { 0.0 },
+        fast_road_share: length_share(&signed, |e| {
+            if maxspeed_kmh(e).is_some_and(|speed| speed > speed_limit) { 1.0 } else { 0.0 }
+        }),
+        unknown_speed_share: length_share(&roads, |e| if maxspeed_kmh(e).is_none() { 1.0 } else { 0.0 }),
+        sidewalk_share: length_share(&roads, sidewalk_coverage),
+        traffic_calming,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const RADIUS: f64 = 500.0;
+    const DEGREES_PER_M: f64 = 1.0 / 111_320.0;
+
+    // An east-west road `length` metres long at `north` metres from the center.
+    fn road(id: u64, north: f64, length: f64, tags: serde_json::Value) -> OverpassElement {
+        serde_json::from_value(json!({
+            "type": "way", "id": id, "tags": tags,
+            "geometry": [
+                { "lat": north * DEGREES_PER_M, "lon": -length / 2.0 * DEGREES_PER_M },
+                { "lat": north * DEGREES_PER_M, "lon": length / 2.0 * DEGREES_PER_M }
+            ]
+        }))
+        .unwrap()
+    }
+
+    fn speed(value: &str) -> Option<f64> {
+        maxspeed_kmh(&road(1, 0.0, 10.0, json!({ "highway": "primary", "maxspeed": value })))
+    }
+
+    #[test]
+    fn speed_limits_accept_common_units() {
+        assert_eq!(speed("50"), Some(50.0));
+        assert_eq!(speed("50 km/h"), Some(50.0));
+        assert_eq!(speed("50km/h"), Some(50.0));
+        assert_eq!(speed("50 kmh"), Some(50.0));
+        assert!((speed("30 mph").unwrap() - 48.28).abs() < 0.01);
+        assert!((speed("30mph").unwrap() - 48.28).abs() < 0.01);
+        assert_eq!(speed("DE:urban"), None);
+        assert_eq!(speed("walk"), None);
+        assert_eq!(speed("50 knots"), None);
+    }
+
+    #[test]
+    fn unknown_limits_are_kept_out_of_the_fast_share() {
+        let elements = vec![
+            road(1, 0.0, 200.0, json!({ "highway": "trunk", "maxspeed": "80 km/h" })),
+            road(2, 10.0, 200.0, json!({ "highway": "residential", "maxspeed": "30" })),
+            road(3, 20.0, 400.0, json!({ "highway": "residential" })),
+        ];
+        let summary = road_safety_summary(&elements, 0.0, 0.0, RADIUS);
+        assert!((summary.road_length_m - 800.0).abs() < 1.0);
+        assert!((summary.fast_road_share - 0.5).abs() < 0.01);
+        assert!((summary.unknown_speed_share - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn footways_are_not_roads() {
+        let elements = vec![
+            road(1, 0.0, 100.0, json!({ "highway": "footway" })),
+            road(2, 10.0, 100.0, json!({ "highway": "trunk_link", "sidewalk": "left" })),
+        ];
+        let summary = road_safety_summary(&elements, 0.0, 0.0, RADIUS);
+        assert!((summary.road_length_m - 100.0).abs() < 1.0);
+        assert!((summary.sidewalk_share - 0.5).abs() < 0.01);
+        assert_eq!(summary.unknown_speed_share, 1.0);
+    }
+
+    #[test]
+    fn sidewalk_tags_cover_both_sides() {
+        let coverage = |tags: serde_json::Value| sidewalk_coverage(&road(1, 0.0, 10.0, tags));
+        assert_eq!(coverage(json!({ "sidewalk": "both" })), 1.0);
+        assert_eq!(coverage(json!({ "sidewalk": "right" })), 0.5);
+        assert_eq!(coverage(json!({ "sidewalk:both": "separate" })), 1.0);
+        assert_eq!(coverage(json!({ "sidewalk:left": "yes", "sidewalk:right": "no" })), 0.5);
+        assert_eq!(coverage(json!({ "sidewalk": "no" })), 0.0);
+    }
+
+    #[test]
+    fn no_roads_gives_zero_shares() {
+        let summary = road_safety_summary(&[], 0.0, 0.0, RADIUS);
+        assert_eq!(
+            (summary.road_length_m, summary.crossings_per_km, summary.fast_road_share, summary.unknown_speed_share),
+            (0.0, 0.0, 0.0, 0.0)
+        );
+    }
+}
@@ -1,10 +1,13 @@
 use crate::models::{Facility, FacilityCounts, Scores, OverpassElement};
 use rayon::prelude::*;
 use once_cell::sync::Lazy;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use serde_json::{json, Value};
 
-use crate::services::category_detection::detect_category;
+use crate::services::category_detection::{detect_category, detect_subcategory};
+use crate::services::dedup::{merge_same_places, unique_elements};
+use crate::services::area_scoring::{element_outline, element_position, facility_distance};
+use crate::services::geo::ring_area_m2;
 
 pub static SCORING_CONFIG: Lazy<Value> = Lazy::new(|| {
     std::fs::read_to_string("config/scoring_config.json")
@@ -13,15 +16,18 @@ pub static SCORING_CONFIG: Lazy<Value> = Lazy::new(|| {
         .unwrap_or(json!({}))
 });
 
-const R: f64 = 6_371_000.0;
 const MAX_FACILITY_DISTANCE: f64 = 500.0;
 
-fn get_contribution_weights(config: &Value, category: &str) -> (f64, f64, f64) {
+fn get_contribution_weights(
+    config: &Value,
+    category: &str,
+    subcategory: Option<&str>,
+) -> (f64, f64, f64) {
     const DEFAULT_MAX_CONTRIB: f64 = 10.0;
     const DEFAULT_DECAY: f64 = 0.8;
     const DEFAULT_MIN_RATIO: f64 = 0.1;
     
-    config["contribution_weights"]
+    let (max_contrib, decay, min_ratio) = config["contribution_weights"]
         .get(category)
         .or_else(|| config["contribution_weights"].get("default"))
         .and_then(|w| {
@@ -30,7 +36,18 @@ fn get_contribution_weights(config: &Value, category: &str) -> (f64, f64, f64) {
             let min_ratio = w["min_contribution_ratio"].as_f64()?;
             Some((max_contrib, decay, min_ratio))
         })
-        .unwrap_or((DEFAULT_MAX_CONTRIB, DEFAULT_DECAY, DEFAULT_MIN_RATIO))
+        .unwrap_or((DEFAULT_MAX_CONTRIB, DEFAULT_DECAY, DEFAULT_MIN_RATIO));
+
+    subcategory
+        .and_then(|sub| config["subcategory_weights"].get(category)?.get(sub))
+        .map(|w| {
+            (
+                w["max_contribution"].as_f64().unwrap_or(max_contrib),
+                w["decay_factor"].as_f64().unwrap_or(decay),
+                w["min_contribution_ratio"].as_f64().unwrap_or(min_ratio),
+            )
+        })
+        .unwrap_or((max_contrib, decay, min_ratio))
 }
 
 fn get_score_weights(config: &Value) -> (f64, f64, f64, f64, f64) {
@@ -130,19 +147,6 @@ fn calculate_category_group_score(
     }
 }
 
-fn calculate_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
-    let d_lat = (lat2 - lat1).to_radians();
-    let d_lon = (lon2 - lon1).to_radians();
-
-    let a = (d_lat / 2.0).sin().powi(2)
-        + lat1.to_radians().cos()
-            * lat2.to_radians().cos()
-            * (d_lon / 2.0).sin().powi(2);
-
-    let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
-    R * c
-}
-
 fn is_within_distance_threshold(distance: f64) -> bool {
     distance <= MAX_FACILITY_DISTANCE
 }
@@ -151,9 +155,15 @@ fn normalize_distance(distance: f64) -> f64 {
     distance / MAX_FACILITY_DISTANCE
 }
 
-fn calculate_contribution(distance: f64, category: &str, config: &Value) -> f64 {
+fn calculate_contribution(
+    distance: f64,
+    category: &str,
+    subcategory: Option<&str>,
+    config: &Value,
+) -> f64 {
     if is_within_distance_threshold(distance) {
-        let (max_contribution, decay, min_ratio) = get_contribution_weights(config, category);
+        let (max_contribution, decay, min_ratio) =
+            get_contribution_weights(config, category, subcategory);
         let norm = normalize_distance(distance);
         let contribution = max_contribution * (1.0 - norm).powf(decay);
         let min_contribution = max_contribution * min_ratio;
@@ -163,47 +173,97 @@ fn calculate_contribution(distance: f64, category: &str, config: &Value) -> f64
     }
 }
 
-pub fn process_facilities(
-    elements: &[OverpassElement],
-    user_lat: f64,
-    user_lng: f64,
-) -> Vec<Facility> {
-    static EMPTY: Lazy<HashMap<String, String>> = Lazy::new(|| HashMap::new());
+const OSM_BROWSE_URL: &str = "https://www.openstreetmap.org";
+
+// Node, way and relation ids are separate namespaces, so the type is part of the key.
+fn osm_key(element_type: &str, id: u64) -> String {
+    format!("{}/{}", element_type, id)
+}
+
+fn osm_url(element_type: &str, id: u64) -> String {
+    format!("{}/{}", OSM_BROWSE_URL, osm_key(element_type, id))
+}
+
+// Classification does not depend on the origin, so an area fetch can be classified
+// once and then measured against many points; distance and contribution start at zero.
+pub fn classify_elements(elements: &[OverpassElement], patterns: &Value) -> Vec<Facility> {
+    static EMPTY: Lazy<HashMap<String, String>> = Lazy::new(HashMap::new);
 
     elements
         .par_iter()
         .filter_map(|element| {
-            let lat = element.lat.or_else(|| element.center.as_ref().map(|c| c.lat))?;
-            let lng = element.lon.or_else(|| element.center.as_ref().map(|c| c.lon))?;
-
-            let distance = calculate_distance(user_lat, user_lng, lat, lng);
+            let (lat, lng) = element_position(element)?;
 
             let tags_ref = element.tags.as_ref().unwrap_or(&EMPTY);
 
             let name = extract_facility_name(tags_ref, &SCORING_CONFIG);
 
-            let actual_category = detect_category(tags_ref, &name)?;
+            let actual_category = detect_category(patterns, tags_ref, &name)?;
 
-            let contribution = calculate_contribution(distance, actual_category, &SCORING_CONFIG);
+            let subcategory = detect_subcategory(patterns, actual_category, tags_ref, &name);
 
-            if contribution <= 0.0 {
-                return None;
-            }
+            let outline = element_outline(element);
 
             Some(Facility {
-                id: format!("{}-{}", actual_category, element.id),
+                id: osm_key(&element.element_type, element.id),
+                osm_type: element.element_type.clone(),
+                osm_url: osm_url(&element.element_type, element.id),
                 name,
                 category: actual_category.to_string(),
+                subcategory,
                 lng,
                 lat,
-                distance,
-                contribution,
-                tags: element.tags.clone(),
+                distance: 0.0,
+                contribution: 0.0,
+                tags: element.tags.as_ref().map(|tags| tags.clone().into_iter().collect()),
+                area_m2: outline.as_ref().map(|ring| ring_area_m2(ring)),
+                outline: outline.map(|ring| ring.into_iter().map(|(lat, lng)| [lng, lat]).collect()),
             })
         })
         .collect()
 }
 
+// Everything fetched for one area, classified once per physical place.
+pub fn classify_fetched(fetched: &[(String, Vec<OverpassElement>)], patterns: &Value) -> Vec<Facility> {
+    merge_same_places(classify_elements(&unique_elements(fetched), patterns))
+}
+
+// `catchment` stretches the distance decay: a facility at the catchment edge scores like
+// one at the standard maximum distance.
+pub fn measure_facility(facility: &Facility, distance: f64, catchment: f64) -> Option<Facility> {
+    let contribution = calculate_contribution(
+        distance * MAX_FACILITY_DISTANCE / catchment,
+        &facility.category,
+        facility.subcategory.as_deref(),
+        &SCORING_CONFIG,
+    );
+
+    if contribution <= 0.0 {
+        return None;
+    }
+
+    Some(Facility {
+        distance,
+        contribution,
+        ..facility.clone()
+    })
+}
+
+pub fn measure_facilities<'a>(
+    classified: impl IntoIterator<Item = &'a Facility>,
+    user_lat: f64,
+    user_lng: f64,
+    catchment: f64,
+) -> Vec<Facility> {
+    classified
+        .into_iter()
+        .filter_map(|facility| {
+            let distance = facility_distance(facility, user_lat, user_lng);
+            measure_facility(facility, distance, catchment)
+        })
+        .collect()
+}
+
 fn increment_category_count(mut counts: FacilityCounts, category: &str) -> FacilityCounts {
     match category {
         "health" => counts.health += 1,
@@ -230,7 +290,33 @@ fn update_contribution_map(
     map
 }
 
+pub fn count_subcategories(facilities: &[Facility]) -> BTreeMap<String, BTreeMap<String, usize>> {
+    facilities
+        .iter()
+        .filter_map(|f| f.subcategory.as_ref().map(|sub| (&f.category, sub)))
+        .fold(BTreeMap::new(), |mut acc, (category, sub)| {
+            *acc.entry(category.clone())
+                .or_insert_with(BTreeMap::new)
+                .entry(sub.clone())
+                .or_insert(0) += 1;
+            acc
+        })
+}
+
 pub fn calculate_scores(facilities: &[Facility]) -> (Scores, FacilityCounts) {
+    calculate_adjusted_scores(facilities, &[])
+}
+
+// Groups that `adjustments` can target; any other group name would be ignored.
+pub const SCORE_GROUPS: [&str; 4] = ["services", "mobility", "safety", "environment"];
+
+// Layers that adjust point scores on top of facility contributions. Heatmap cells and
+// area targets are scored without them and list them as `not_computed` instead.
+pub const CONTEXT_LAYERS: [&str; 4] = ["green_space", "nuisances", "night_safety", "road_safety"];
+
+// `adjustments` are (group, points) pairs added to a group's raw score before
+// clamping, for signals that are not facility contributions.
+pub fn calculate_adjusted_scores(facilities: &[Facility], adjustments: &[(&str, f64)]) -> (Scores, FacilityCounts) {
     let (counts, map) = facilities.iter().fold(
         (FacilityCounts::default(), HashMap::new()),
         |(counts, map), f| {
@@ -246,26 +332,33 @@ pub fn calculate_scores(facilities: &[Facility]) -> (Scores, FacilityCounts) {
     let category_mappings = get_category_mappings(&SCORING_CONFIG);
     let (services_w, mobility_w, safety_w, environment_w, health_to_safety) = get_score_weights(&SCORING_CONFIG);
     let health_contribution = *map.get("health").unwrap_or(&0.0);
+    let adjustment = |group: &str| -> f64 {
+        adjustments.iter().filter(|(g, _)| *g == group).map(|(_, points)| points).sum()
+    };
 
     let services_score = category_mappings
         .get("services")
         .map(|cats| calculate_category_group_score(&map, cats, health_contribution, health_to_safety))
-        .unwrap_or(0.0);
+        .unwrap_or(0.0)
+        + adjustment("services");
 
     let mobility_score = category_mappings
         .get("mobility")
         .map(|cats| calculate_category_group_score(&map, cats, health_contribution, health_to_safety))
-        .unwrap_or(0.0);
+        .unwrap_or(0.0)
+        + adjustment("mobility");
 
     let safety_score = category_mappings
         .get("safety")
         .map(|cats| calculate_category_group_score(&map, cats, health_contribution, health_to_safety))
-        .unwrap_or(0.0);
+        .unwrap_or(0.0)
+        + adjustment("safety");
 
     let environment_score = category_mappings
         .get("environment")
         .map(|cats| calculate_category_group_score(&map, cats, health_contribution, health_to_safety))
-        .unwrap_or(0.0);
+        .unwrap_or(0.0)
+        + adjustment("environment");
 
     let services_normalized = normalize(services_score);
     let mobility_normalized = normalize(mobility_score);
@@ -288,3 +381,72 @@ pub fn calculate_scores(facilities: &[Facility]) -> (Scores, FacilityCounts) {
 
     (scores, counts)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::category_detection::PATTERN_CONFIG;
+
+    fn facility(category: &str, subcategory: Option<&str>) -> Facility {
+        serde_json::from_value(json!({
+            "id": "node/1", "name": "x", "category": category, "subcategory": subcategory,
+            "lat": 0.0, "lng": 0.0, "distance": 0.0, "contribution": 0.0,
+        }))
+        .unwrap()
+    }
+
+    fn weights() -> Value {
+        json!({
+            "contribution_weights": {
+                "default": { "max_contribution": 10.0, "decay_factor": 1.0, "min_contribution_ratio": 0.1 },
+                "health": { "max_contribution": 20.0, "decay_factor": 0.5, "min_contribution_ratio": 0.2 }
+            },
+            "subcategory_weights": { "health": { "hospital": { "max_contribution": 30.0 } } }
+        })
+    }
+
+    #[test]
+    fn subcategory_weights_override_only_the_fields_they_set() {
+        assert_eq!(get_contribution_weights(&weights(), "health", Some("hospital")), (30.0, 0.5, 0.2));
+        assert_eq!(get_contribution_weights(&weights(), "health", Some("clinic")), (20.0, 0.5, 0.2));
+        assert_eq!(get_contribution_weights(&weights(), "market", Some("hospital")), (10.0, 1.0, 0.1));
+    }
+
+    #[test]
+    fn contribution_has_a_floor_inside_the_radius_and_none_beyond() {
+        assert_eq!(calculate_contribution(0.0, "market", None, &weights()), 10.0);
+        assert_eq!(calculate_contribution(MAX_FACILITY_DISTANCE, "market", None, &weights()), 1.0);
+        assert_eq!(calculate_contribution(MAX_FACILITY_DISTANCE + 1.0, "market", None, &weights()), 0.0);
+    }
+
+    #[test]
+    fn subcategories_are_counted_per_category() {
+        let facilities = vec![
+            facility("health", Some("hospital")),
+            facility("health", Some("hospital")),
+            facility("health", None),
+            facility("market", Some("hospital")),
+        ];
+        let counts = count_subcategories(&facilities);
+        assert_eq!(counts["health"]["hospital"], 2);
+        assert_eq!(counts["market"]["hospital"], 1);
+        assert_eq!(counts.len(), 2);
+        assert!(count_subcategories(&[]).is_empty());
+    }
+
+    #[test]
+    fn one_object_fetched_by_several_category_queries_is_classified_once() {
+        let pharmacy: OverpassElement = serde_json::from_value(json!({
+            "type": "node", "id": 42, "lat": 0.0, "lon": 0.0,
+            "tags": { "amenity": "pharmacy", "name": "Apotek Sehat" }
+        }))
+        .unwrap();
+        let fetched = vec![
+            ("health".to_string(), vec![pharmacy.clone()]),
+            ("market".to_string(), vec![pharmacy]),
+        ];
+        let classified = classify_fetched(&fetched, &PATTERN_CONFIG);
+        assert_eq!(classified.len(), 1);
+        assert_eq!(classified[0].category, "health");
+    }
+}
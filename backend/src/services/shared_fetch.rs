@@ -0,0 +1,124 @@
+use serde_json::Value;
+
+use crate::models::SingleLocationRequest;
+use crate::services::geo::{bounds_span, calculate_distance, pad_bounds};
+use crate::services::language_packs::resolve_locale;
+
+// Locations closer than this have overlapping search circles worth fetching together.
+const SHARED_FETCH_DISTANCE_M: f64 = 1000.0;
+const MAX_SHARED_SPAN_M: f64 = 3000.0;
+// Slack so edge members stay inside the fetched bounds despite projection rounding.
+const SHARED_FETCH_MARGIN_M: f64 = 50.0;
+
+pub struct SharedArea {
+    pub packs: Vec<String>,
+    pub patterns: Value,
+    pub bbox: [f64; 4],
+    pub members: Vec<usize>,
+}
+
+fn member_bounds(points: &[Option<(f64, f64)>], members: &[usize]) -> [f64; 4] {
+    members.iter().filter_map(|&i| points[i]).fold(
+        [f64::MAX, f64::MAX, f64::MIN, f64::MIN],
+        |[min_lat, min_lng, max_lat, max_lng], (lat, lng)| {
+            [min_lat.min(lat), min_lng.min(lng), max_lat.max(lat), max_lng.max(lng)]
+        },
+    )
+}
+
+// Greedy grouping: a location joins the first group with the same language packs that
+// has a member within reach, as long as the padded group stays small enough to fetch.
+// Polygon and relation locations fetch their own area and never join a group.
+pub fn plan_shared_areas(locations: &[SingleLocationRequest], radius: f64) -> Vec<SharedArea> {
+    let padding = radius + SHARED_FETCH_MARGIN_M;
+    let points: Vec<Option<(f64, f64)>> = locations.iter().map(|loc| loc.point()).collect();
+
+    let groups = locations.iter().enumerate().fold(Vec::<SharedArea>::new(), |mut groups, (i, loc)| {
+        let (lat, lng) = match points[i] {
+            Some(point) => point,
+            None => return groups,
+        };
+        let locale = resolve_locale(lat, lng, loc.country.as_deref());
+        let joinable = groups.iter().position(|group| {
+            let mut members = group.members.clone();
+            members.push(i);
+            let (height, width) = bounds_span(pad_bounds(member_bounds(&points, &members), padding));
+
+            group.packs == locale.packs
+                && height <= MAX_SHARED_SPAN_M
+                && width <= MAX_SHARED_SPAN_M
+                && group.members.iter().filter_map(|&m| points[m]).any(|(m_lat, m_lng)| {
+                    calculate_distance(m_lat, m_lng, lat, lng) <= SHARED_FETCH_DISTANCE_M
+                })
+        });
+
+        match joinable {
+            Some(g) => groups[g].members.push(i),
+            None => groups.push(SharedArea {
+                packs: locale.packs,
+                patterns: locale.patterns,
+                bbox: [0.0; 4],
+                members: vec![i],
+            }),
+        }
+        groups
+    });
+
+    groups
+        .into_iter()
+        .filter(|group| group.members.len() > 1)
+        .map(|group| SharedArea {
+            bbox: pad_bounds(member_bounds(&points, &group.members), padding),
+            ..group
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn point(lat: f64, lng: f64) -> SingleLocationRequest {
+        serde_json::from_value(json!({ "lat": lat, "lng": lng, "country": "ID" })).unwrap()
+    }
+
+    fn members(areas: &[SharedArea]) -> Vec<Vec<usize>> {
+        areas.iter().map(|a| a.members.clone()).collect()
+    }
+
+    #[test]
+    fn nearby_points_share_one_padded_area() {
+        let areas = plan_shared_areas(&[point(-6.2, 106.8), point(-6.2, 106.805)], 500.0);
+        assert_eq!(members(&areas), vec![vec![0, 1]]);
+        let [min_lat, min_lng, max_lat, max_lng] = areas[0].bbox;
+        assert!(min_lat < -6.2 && max_lat > -6.2 && min_lng < 106.8 && max_lng > 106.805);
+    }
+
+    #[test]
+    fn single_and_distant_points_are_not_grouped() {
+        assert!(plan_shared_areas(&[point(-6.2, 106.8)], 500.0).is_empty());
+        assert!(plan_shared_areas(&[point(-6.2, 106.8), point(-6.2, 106.82)], 500.0).is_empty());
+        assert!(plan_shared_areas(&[], 500.0).is_empty());
+    }
+
+    #[test]
+    fn polygon_and_relation_locations_never_join() {
+        let relation: SingleLocationRequest = serde_json::from_value(json!({ "relation": 123 })).unwrap();
+        let areas = plan_shared_areas(&[point(-6.2, 106.8), relation, point(-6.2, 106.801)], 500.0);
+        assert_eq!(members(&areas), vec![vec![0, 2]]);
+    }
+
+    #[test]
+    fn groups_stop_growing_past_the_span_limit() {
+        // A chain of points 900 m apart: each is within reach of the previous one, but
+        // the padded group may not exceed the maximum span.
+        let chain: Vec<SingleLocationRequest> = (0..5).map(|i| point(-6.2, 106.8 + i as f64 * 0.0081)).collect();
+        let areas = plan_shared_areas(&chain, 500.0);
+        assert!(areas.iter().all(|a| {
+            let (height, width) = bounds_span(a.bbox);
+            height <= MAX_SHARED_SPAN_M && width <= MAX_SHARED_SPAN_M
+        }));
+        assert_eq!(members(&areas), vec![vec![0, 1, 2], vec![3, 4]]);
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::models::Facility;
+use crate::services::area_scoring::{facility_distance, outline_extent};
+use crate::services::geo::{calculate_distance, meters_per_degree_lng, pad_bounds, METERS_PER_DEGREE};
+
+// Fixed-size lat/lng buckets. A radius query only visits the buckets overlapping the
+// query's bounding box and then checks the exact haversine distance.
+pub struct FacilityIndex {
+    facilities: Vec<Facility>,
+    seen_ids: HashSet<String>,
+    buckets: HashMap<(i64, i64), Vec<usize>>,
+    bucket_degrees: f64,
+    // Largest outline reach beyond a center point, widening lookups so areas whose
+    // edge is in range are found even when their center is not.
+    max_extent: f64,
+}
+
+impl FacilityIndex {
+    pub fn new(bucket_size_m: f64) -> Self {
+        FacilityIndex {
+            facilities: Vec::new(),
+            seen_ids: HashSet::new(),
+            buckets: HashMap::new(),
+            bucket_degrees: bucket_size_m.max(1.0) / METERS_PER_DEGREE,
+            max_extent: 0.0,
+        }
+    }
+
+    // Facilities already indexed under the same id are skipped, so overlapping
+    // fetches can be added without double counting.
+    pub fn extend(&mut self, facilities: impl IntoIterator<Item = Facility>) {
+        facilities.into_iter().for_each(|facility| {
+            if self.seen_ids.insert(facility.id.clone()) {
+                let key = bucket_key(facility.lat, facility.lng, self.bucket_degrees);
+                self.max_extent = self.max_extent.max(outline_extent(&facility));
+                self.buckets.entry(key).or_default().push(self.facilities.len());
+                self.facilities.push(facility);
+            }
+        });
+    }
+
+    pub fn facility_count(&self) -> usize {
+        self.facilities.len()
+    }
+
+    pub fn within(&self, lat: f64, lng: f64, radius: f64) -> Vec<&Facility> {
+        let reach = radius + self.max_extent;
+        let d_lat = reach / METERS_PER_DEGREE;
+        let d_lng = reach / meters_per_degree_lng(lat);
+        let (min_row, min_col) = bucket_key(lat - d_lat, lng - d_lng, self.bucket_degrees);
+        let (max_row, max_col) = bucket_key(lat + d_lat, lng + d_lng, self.bucket_degrees);
+
+        let mut hits: Vec<usize> = (min_row..=max_row)
+            .flat_map(|row| (min_col..=max_col).map(move |col| (row, col)))
+            .filter_map(|key| self.buckets.get(&key))
+            .flatten()
+            .copied()
+            .filter(|&i| facility_distance(&self.facilities[i], lat, lng) <= radius)
+            .collect();
+
+        // Keep insertion order so results do not depend on bucket iteration.
+        hits.sort_unstable();
+        hits.into_iter().map(|i| &self.facilities[i]).collect()
+    }
+}
+
+fn bucket_key(lat: f64, lng: f64, bucket_degrees: f64) -> (i64, i64) {
+    (
+        (lat / bucket_degrees).floor() as i64,
+        (lng / bucket_degrees).floor() as i64,
+    )
+}
+
+#[derive(Clone, Copy)]
+pub enum FetchedArea {
+    Circle { lat: f64, lng: f64, radius: f64 },
+    // [min_lat, min_lng, max_lat, max_lng]
+    Bounds([f64; 4]),
+}
+
+impl FetchedArea {
+    fn covers_circle(&self, lat: f64, lng: f64, radius: f64) -> bool {
+        match *self {
+            FetchedArea::Circle { lat: c_lat, lng: c_lng, radius: c_radius } => {
+                calculate_distance(c_lat, c_lng, lat, lng) + radius <= c_radius
+            }
+            FetchedArea::Bounds([min_lat, min_lng, max_lat, max_lng]) => {
+                let [s, w, n, e] = pad_bounds([lat, lng, lat, lng], radius);
+                s >= min_lat && n <= max_lat && w >= min_lng && e <= max_lng
+            }
+        }
+    }
+}
+
+struct LocaleAreas {
+    packs: Vec<String>,
+    areas: Vec<FetchedArea>,
+    index: FacilityIndex,
+}
+
+// Request-scoped store of everything fetched so far. Classification depends on the
+// language packs, so areas are only reused by locations resolving to the same packs.
+pub struct AreaCache {
+    bucket_size_m: f64,
+    locales: Vec<LocaleAreas>,
+}
+
+impl AreaCache {
+    pub fn new(bucket_size_m: f64) -> Self {
+        AreaCache { bucket_size_m, locales: Vec::new() }
+    }
+
+    pub fn covering(&self, packs: &[String], lat: f64, lng: f64, radius: f64) -> Option<&FacilityIndex> {
+        self.locales
+            .iter()
+            .find(|locale| locale.packs == packs)
+            .filter(|locale| locale.areas.iter().any(|area| area.covers_circle(lat, lng, radius)))
+            .map(|locale| &locale.index)
+    }
+
+    pub fn insert(&mut self, packs: &[String], area: FetchedArea, facilities: Vec<Facility>) {
+        let position = match self.locales.iter().position(|locale| locale.packs == packs) {
+            Some(position) => position,
+            None => {
+                self.locales.push(LocaleAreas {
+                    packs: packs.to_vec(),
+                    areas: Vec::new(),
+                    index: FacilityIndex::new(self.bucket_size_m),
+                });
+                self.locales.len() - 1
+            }
+        };
+
+        let locale = &mut self.locales[position];
+        locale.areas.push(area);
+        locale.index.extend(facilities);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn facility(id: &str, lat: f64, lng: f64) -> Facility {
+        serde_json::from_value(json!({
+            "id": id, "name": id, "category": "market", "lat": lat, "lng": lng,
+            "distance": 0.0, "contribution": 0.0,
+        }))
+        .unwrap()
+    }
+
+    fn ids(facilities: Vec<&Facility>) -> Vec<&str> {
+        facilities.iter().map(|f| f.id.as_str()).collect()
+    }
+
+    #[test]
+    fn duplicate_ids_are_indexed_once() {
+        let mut index = FacilityIndex::new(100.0);
+        index.extend([facility("node/1", 0.0, 0.0), facility("node/1", 0.0, 0.0005)]);
+        index.extend([facility("node/1", 0.0, 0.0)]);
+        assert_eq!(index.facility_count(), 1);
+        assert_eq!(index.within(0.0, 0.0, 10.0)[0].lng, 0.0);
+    }
+
+    #[test]
+    fn radius_is_exact_across_bucket_edges_and_signs() {
+        let mut index = FacilityIndex::new(100.0);
+        // About 111 m apart, straddling the zero meridian and several buckets.
+        index.extend([facility("a", -6.2, -0.0005), facility("b", -6.2, 0.0005), facility("c", -6.2, 0.01)]);
+        assert_eq!(ids(index.within(-6.2, 0.0, 60.0)), vec!["a", "b"]);
+        assert_eq!(ids(index.within(-6.2, 0.0, 50.0)), Vec::<&str>::new());
+        assert_eq!(ids(index.within(-6.2, -0.0005, 0.0)), vec!["a"]);
+    }
+
+    #[test]
+    fn results_keep_insertion_order() {
+        let mut index = FacilityIndex::new(50.0);
+        index.extend([facility("far", 0.0, 0.003), facility("near", 0.0, 0.0001), facility("mid", 0.0, -0.002)]);
+        assert_eq!(ids(index.within(0.0, 0.0, 500.0)), vec!["far", "near", "mid"]);
+    }
+
+    #[test]
+    fn outlined_facilities_are_found_by_their_edge() {
+        let mut park = facility("way/1", 0.0, 0.01);
+        park.outline = Some(vec![[0.002, -0.01], [0.018, -0.01], [0.018, 0.01], [0.002, 0.01], [0.002, -0.01]]);
+        let mut index = FacilityIndex::new(100.0);
+        index.extend([park]);
+        // Center is over 1 km away, the western edge about 220 m.
+        assert_eq!(index.within(0.0, 0.0, 250.0).len(), 1);
+        assert!(index.within(0.0, 0.0, 200.0).is_empty());
+    }
+
+    #[test]
+    fn empty_index_finds_nothing() {
+        assert!(FacilityIndex::new(0.0).within(0.0, 0.0, 1000.0).is_empty());
+    }
+
+    #[test]
+    fn cached_areas_are_reused_only_when_they_cover_the_circle() {
+        let packs = vec!["id".to_string()];
+        let mut cache = AreaCache::new(100.0);
+        cache.insert(&packs, FetchedArea::Circle { lat: 0.0, lng: 0.0, radius: 1000.0 }, vec![]);
+        cache.insert(&packs, FetchedArea::Bounds([1.0, 1.0, 1.1, 1.1]), vec![]);
+
+        assert!(cache.covering(&packs, 0.0, 0.004, 500.0).is_some());
+        assert!(cache.covering(&packs, 0.0, 0.005, 500.0).is_none());
+        assert!(cache.covering(&packs, 1.05, 1.05, 500.0).is_some());
+        assert!(cache.covering(&packs, 1.001, 1.05, 500.0).is_none());
+        assert!(cache.covering(&["en".to_string()], 0.0, 0.0, 10.0).is_none());
+    }
+}
@@ -0,0 +1,407 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::models::{DistanceMode, Facility, OverpassElement, ScoringOptions};
+use crate::services::geo::{calculate_distance, local_xy, meters_per_degree_lng, METERS_PER_DEGREE};
+use crate::services::query_builder::QUERY_CONFIG;
+
+const DEFAULT_MAX_SNAP_DISTANCE: f64 = 100.0;
+const DEFAULT_WALKING_SPEED_KMH: f64 = 4.8;
+const DEFAULT_ISOCHRONE_MINUTES: &[f64] = &[5.0, 10.0, 15.0];
+const SNAP_BUCKET_M: f64 = 100.0;
+const ISOCHRONE_SECTORS: usize = 72;
+pub const MAX_WALK_MINUTES: f64 = 30.0;
+pub const WALKING_SPEED_RANGE_KMH: (f64, f64) = (1.0, 10.0);
+
+pub fn get_max_snap_distance() -> f64 {
+    QUERY_CONFIG["walking_network"]["max_snap_distance"]
+        .as_f64()
+        .unwrap_or(DEFAULT_MAX_SNAP_DISTANCE)
+}
+
+pub fn get_walking_speed_kmh() -> f64 {
+    QUERY_CONFIG["walking_network"]["walking_speed_kmh"]
+        .as_f64()
+        .unwrap_or(DEFAULT_WALKING_SPEED_KMH)
+}
+
+pub fn get_isochrone_minutes() -> Vec<f64> {
+    QUERY_CONFIG["walking_network"]["isochrone_minutes"]
+        .as_array()
+        .map(|arr| arr.iter().filter_map(|v| v.as_f64()).collect::<Vec<_>>())
+        .filter(|minutes| !minutes.is_empty())
+        .unwrap_or_else(|| DEFAULT_ISOCHRONE_MINUTES.to_vec())
+}
+
+pub fn minutes_to_meters(minutes: f64, speed_kmh: f64) -> f64 {
+    minutes * speed_kmh * 1000.0 / 60.0
+}
+
+pub fn meters_to_minutes(meters: f64, speed_kmh: f64) -> f64 {
+    meters * 60.0 / (speed_kmh * 1000.0)
+}
+
+pub fn walking_speed(options: &ScoringOptions) -> f64 {
+    options.walking_speed_kmh.unwrap_or_else(get_walking_speed_kmh)
+}
+
+pub fn uses_walking_distance(options: &ScoringOptions) -> bool {
+    options.distance_mode == DistanceMode::Walking || options.walk_minutes.is_some()
+}
+
+// "Reachable within N minutes" replaces the default radius as the scoring catchment.
+pub fn catchment_distance(options: &ScoringOptions, default: f64) -> f64 {
+    options
+        .walk_minutes
+        .map(|minutes| minutes_to_meters(minutes, walking_speed(options)))
+        .unwrap_or(default)
+}
+
+pub fn isochrone_budgets(options: &ScoringOptions) -> Vec<f64> {
+    match &options.isochrone_minutes {
+        Some(minutes) if !minutes.is_empty() => minutes.clone(),
+        _ if options.isochrones || options.isochrone_minutes.is_some() => get_isochrone_minutes(),
+        _ => Vec::new(),
+    }
+}
+
+pub struct WalkingGraph {
+    nodes: Vec<(f64, f64)>,
+    adjacency: Vec<Vec<(usize, f64)>>,
+    buckets: HashMap<(i64, i64), Vec<usize>>,
+}
+
+fn bucket_key(lat: f64, lng: f64) -> (i64, i64) {
+    let size = SNAP_BUCKET_M / METERS_PER_DEGREE;
+    ((lat / size).floor() as i64, (lng / size).floor() as i64)
+}
+
+impl WalkingGraph {
+    // Ways fetched with `out geom` carry node ids and coordinates side by side; shared
+    // node ids are what connect the ways into a network.
+    pub fn from_ways(elements: &[OverpassElement]) -> Self {
+        let mut ids: HashMap<u64, usize> = HashMap::new();
+        let mut graph = WalkingGraph { nodes: Vec::new(), adjacency: Vec::new(), buckets: HashMap::new() };
+
+        elements
+            .iter()
+            .filter(|e| e.element_type == "way")
+            .filter_map(|e| Some((e.nodes.as_ref()?, e.geometry.as_ref()?)))
+            .filter(|(nodes, geometry)| nodes.len() == geometry.len())
+            .for_each(|(nodes, geometry)| {
+                let path: Vec<usize> = nodes
+                    .iter()
+                    .zip(geometry)
+                    .map(|(&id, point)| {
+                        *ids.entry(id).or_insert_with(|| graph.add_node(point.lat, point.lon))
+                    })
+                    .collect();
+
+                path.windows(2).for_each(|pair| {
+                    let (a, b) = (pair[0], pair[1]);
+                    let (lat_a, lng_a) = graph.nodes[a];
+                    let (lat_b, lng_b) = graph.nodes[b];
+                    let length = calculate_distance(lat_a, lng_a, lat_b, lng_b);
+                    graph.adjacency[a].push((b, length));
+                    graph.adjacency[b].push((a, length));
+                });
+            });
+
+        graph
+    }
+
+    fn add_node(&mut self, lat: f64, lng: f64) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push((lat, lng));
+        self.adjacency.push(Vec::new());
+        self.buckets.entry(bucket_key(lat, lng)).or_default().push(index);
+        index
+    }
+
+    pub fn nearest_node(&self, lat: f64, lng: f64, max_distance: f64) -> Option<(usize, f64)> {
+        let reach_lat = (max_distance / SNAP_BUCKET_M).ceil() as i64;
+        let reach_lng = (max_distance / (meters_per_degree_lng(lat) / METERS_PER_DEGREE * SNAP_BUCKET_M)).ceil() as i64;
+        let (row, col) = bucket_key(lat, lng);
+
+        (row - reach_lat..=row + reach_lat)
+            .flat_map(|r| (col - reach_lng..=col + reach_lng).map(move |c| (r, c)))
+            .filter_map(|key| self.buckets.get(&key))
+            .flatten()
+            .map(|&i| (i, calculate_distance(lat, lng, self.nodes[i].0, self.nodes[i].1)))
+            .filter(|(_, distance)| *distance <= max_distance)
+            .min_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)))
+    }
+
+    // Dijkstra from the snapped origin, stopping once paths exceed `limit`.
+    pub fn distances_from(&self, source: usize, initial: f64, limit: f64) -> Vec<f64> {
+        let mut distances = vec![f64::INFINITY; self.nodes.len()];
+        let mut heap = BinaryHeap::new();
+        distances[source] = initial;
+        heap.push(Candidate { cost: initial, node: source });
+
+        while let Some(Candidate { cost, node }) = heap.pop() {
+            if cost > distances[node] {
+                continue;
+            }
+            self.adjacency[node].iter().for_each(|&(next, length)| {
+                let next_cost = cost + length;
+                if next_cost <= limit && next_cost < distances[next] {
+                    distances[next] = next_cost;
+                    heap.push(Candidate { cost: next_cost, node: next });
+                }
+            });
+        }
+
+        distances
+    }
+}
+
+#[derive(PartialEq)]
+struct Candidate {
+    cost: f64,
+    node: usize,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost).then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Shortest walking distances from one origin, reusable for every facility around it.
+pub struct WalkingDistances<'a> {
+    graph: &'a WalkingGraph,
+    distances: Vec<f64>,
+    snap_distance: f64,
+}
+
+impl<'a> WalkingDistances<'a> {
+    pub fn from_origin(graph: &'a WalkingGraph, lat: f64, lng: f64, limit: f64) -> Option<Self> {
+        let snap_distance = get_max_snap_distance();
+        let (source, offset) = graph.nearest_node(lat, lng, snap_distance)?;
+        Some(WalkingDistances {
+            graph,
+            distances: graph.distances_from(source, offset, limit),
+            snap_distance,
+        })
+    }
+
+    pub fn distance_to(&self, lat: f64, lng: f64) -> Option<f64> {
+        let (node, offset) = self.graph.nearest_node(lat, lng, self.snap_distance)?;
+        Some(self.distances[node] + offset).filter(|d| d.is_finite())
+    }
+
+    // Outlined facilities are reached at whichever outline vertex is closest on foot.
+    pub fn distance_to_facility(&self, facility: &Facility) -> Option<f64> {
+        facility
+            .outline
+            .iter()
+            .flatten()
+            .map(|&[lng, lat]| (lat, lng))
+            .chain(std::iter::once((facility.lat, facility.lng)))
+            .filter_map(|(lat, lng)| self.distance_to(lat, lng))
+            .min_by(f64::total_cmp)
+    }
+
+    // Reached nodes plus the point where the budget runs out along each edge leaving them.
+    fn frontier(&self, budget: f64) -> Vec<(f64, f64)> {
+        self.distances
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| **d <= budget)
+            .flat_map(|(node, &d)| {
+                let (lat, lng) = self.graph.nodes[node];
+                let partial = self.graph.adjacency[node]
+                    .iter()
+                    .filter(move |(_, length)| d + length > budget && *length > 0.0)
+                    .map(move |&(next, length)| {
+                        let t = (budget - d) / length;
+                        let (next_lat, next_lng) = self.graph.nodes[next];
+                        (lat + (next_lat - lat) * t, lng + (next_lng - lng) * t)
+                    });
+                std::iter::once((lat, lng)).chain(partial)
+            })
+            .collect()
+    }
+
+    // Star-shaped outline: the farthest reachable point in each bearing sector around the
+    // origin, in bearing order. Returns a closed [lng, lat] ring, or None if too few
+    // sectors are reached to form an area.
+    pub fn isochrone(&self, origin_lat: f64, origin_lng: f64, budget: f64) -> Option<Vec<[f64; 2]>> {
+        let sector_width = 360.0 / ISOCHRONE_SECTORS as f64;
+        let farthest = self.frontier(budget).into_iter().fold(
+            vec![None::<(f64, (f64, f64))>; ISOCHRONE_SECTORS],
+            |mut sectors, (lat, lng)| {
+                let (x, y) = local_xy(lat, lng, origin_lat, origin_lng);
+                let reach = (x * x + y * y).sqrt();
+                let bearing = x.atan2(y).to_degrees().rem_euclid(360.0);
+                let sector = ((bearing / sector_width) as usize).min(ISOCHRONE_SECTORS - 1);
+                if sectors[sector].is_none_or(|(best, _)| reach > best) {
+                    sectors[sector] = Some((reach, (lat, lng)));
+                }
+                sectors
+            },
+        );
+
+        let ring: Vec<[f64; 2]> = farthest
+            .into_iter()
+            .flatten()
+            .map(|(_, (lat, lng))| [lng, lat])
+            .collect();
+
+        if ring.len() < 3 {
+            return None;
+        }
+        Some(ring.iter().chain(ring.first()).copied().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // 0.001 degrees of latitude is about 111 m.
+    const STEP: f64 = 0.001;
+
+    fn way(id: u64, nodes: &[(u64, f64, f64)]) -> OverpassElement {
+        serde_json::from_value(json!({
+            "type": "way",
+            "id": id,
+            "nodes": nodes.iter().map(|n| n.0).collect::<Vec<_>>(),
+            "geometry": nodes.iter().map(|n| json!({ "lat": n.1, "lon": n.2 })).collect::<Vec<_>>(),
+        }))
+        .unwrap()
+    }
+
+    // An L-shaped path: north from the origin, then east. Node 9 sits on a separate way
+    // that shares no node with it.
+    fn network() -> Vec<OverpassElement> {
+        vec![
+            way(1, &[(1, 0.0, 0.0), (2, STEP, 0.0), (3, 2.0 * STEP, 0.0)]),
+            way(2, &[(3, 2.0 * STEP, 0.0), (4, 2.0 * STEP, STEP)]),
+            way(3, &[(9, 0.0, 2.0 * STEP), (10, 0.0, 3.0 * STEP)]),
+        ]
+    }
+
+    #[test]
+    fn shared_node_ids_connect_ways() {
+        let graph = WalkingGraph::from_ways(&network());
+        let walking = WalkingDistances::from_origin(&graph, 0.0, 0.0, 10_000.0).unwrap();
+        let walked = walking.distance_to(2.0 * STEP, STEP).unwrap();
+        assert!((walked - 3.0 * STEP * METERS_PER_DEGREE).abs() < 1.0);
+    }
+
+    #[test]
+    fn disconnected_ways_are_unreachable() {
+        let graph = WalkingGraph::from_ways(&network());
+        let walking = WalkingDistances::from_origin(&graph, 0.0, 0.0, 10_000.0).unwrap();
+        assert!(walking.distance_to(0.0, 2.0 * STEP).is_none());
+    }
+
+    #[test]
+    fn search_stops_at_the_limit() {
+        let graph = WalkingGraph::from_ways(&network());
+        let walking = WalkingDistances::from_origin(&graph, 0.0, 0.0, 150.0).unwrap();
+        assert!(walking.distance_to(STEP, 0.0).is_some());
+        assert!(walking.distance_to(2.0 * STEP, 0.0).is_none());
+    }
+
+    #[test]
+    fn snapping_respects_the_max_distance_and_breaks_ties_by_index() {
+        let graph = WalkingGraph::from_ways(&network());
+        assert_eq!(graph.nearest_node(0.0, 0.0, 1.0).map(|(node, _)| node), Some(0));
+        assert!(graph.nearest_node(0.5, 0.5, 100.0).is_none());
+        // Midway between the first two nodes.
+        assert_eq!(graph.nearest_node(STEP / 2.0, 0.0, 100.0).map(|(node, _)| node), Some(0));
+    }
+
+    #[test]
+    fn ways_without_matching_geometry_are_skipped() {
+        let mut broken = way(5, &[(1, 0.0, 0.0), (2, STEP, 0.0)]);
+        broken.nodes = Some(vec![1]);
+        let graph = WalkingGraph::from_ways(&[broken]);
+        assert!(WalkingDistances::from_origin(&graph, 0.0, 0.0, 1000.0).is_none());
+    }
+
+    #[test]
+    fn empty_network_has_no_origin() {
+        let graph = WalkingGraph::from_ways(&[]);
+        assert!(WalkingDistances::from_origin(&graph, 0.0, 0.0, 1000.0).is_none());
+    }
+
+    // A plus sign of four 222 m arms around the origin.
+    fn cross() -> Vec<OverpassElement> {
+        vec![
+            way(20, &[(20, -2.0 * STEP, 0.0), (21, 0.0, 0.0), (22, 2.0 * STEP, 0.0)]),
+            way(21, &[(23, 0.0, -2.0 * STEP), (21, 0.0, 0.0), (24, 0.0, 2.0 * STEP)]),
+        ]
+    }
+
+    #[test]
+    fn isochrone_reaches_partway_along_edges() {
+        let graph = WalkingGraph::from_ways(&cross());
+        let walking = WalkingDistances::from_origin(&graph, 0.0, 0.0, 1000.0).unwrap();
+        let ring = walking.isochrone(0.0, 0.0, 100.0).unwrap();
+        assert_eq!(ring.first(), ring.last());
+        // Four arm tips at 100 m plus the origin's own sector.
+        let reach = ring.iter().map(|&[lng, lat]| calculate_distance(0.0, 0.0, lat, lng)).fold(0.0, f64::max);
+        assert!((reach - 100.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn too_few_sectors_give_no_isochrone() {
+        let graph = WalkingGraph::from_ways(&network());
+        let walking = WalkingDistances::from_origin(&graph, 0.0, 0.0, 1000.0).unwrap();
+        // Only the origin and one arm to the north are reached.
+        assert!(walking.isochrone(0.0, 0.0, 50.0).is_none());
+    }
+
+    #[test]
+    fn minutes_and_meters_convert_both_ways() {
+        assert_eq!(minutes_to_meters(15.0, 4.0), 1000.0);
+        assert_eq!(meters_to_minutes(1000.0, 4.0), 15.0);
+    }
+
+    #[test]
+    fn walk_minutes_set_the_catchment() {
+        let options = ScoringOptions { walk_minutes: Some(6.0), walking_speed_kmh: Some(5.0), ..Default::default() };
+        assert_eq!(catchment_distance(&options, 500.0), 500.0);
+        assert!(uses_walking_distance(&options));
+        assert_eq!(catchment_distance(&ScoringOptions::default(), 420.0), 420.0);
+        assert!(!uses_walking_distance(&ScoringOptions::default()));
+    }
+
+    #[test]
+    fn isochrone_budgets_default_only_when_requested() {
+        let explicit = ScoringOptions { isochrone_minutes: Some(vec![5.0]), ..Default::default() };
+        let empty_list = ScoringOptions { isochrone_minutes: Some(vec![]), ..Default::default() };
+        let flag = ScoringOptions { isochrones: true, ..Default::default() };
+        assert_eq!(isochrone_budgets(&explicit), vec![5.0]);
+        assert!(!isochrone_budgets(&empty_list).is_empty());
+        assert!(!isochrone_budgets(&flag).is_empty());
+        assert!(isochrone_budgets(&ScoringOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn outlined_facilities_are_reached_at_their_nearest_vertex() {
+        let graph = WalkingGraph::from_ways(&network());
+        let walking = WalkingDistances::from_origin(&graph, 0.0, 0.0, 10_000.0).unwrap();
+        let facility: Facility = serde_json::from_value(json!({
+            "id": "way/7", "name": "Taman", "category": "recreation", "lat": 2.0 * STEP, "lng": STEP,
+            "distance": 0.0, "contribution": 0.0,
+            "outline": [[0.0, STEP], [0.0, 2.0 * STEP], [0.0005, 2.0 * STEP], [0.0, STEP]]
+        }))
+        .unwrap();
+        let walked = walking.distance_to_facility(&facility).unwrap();
+        assert!((walked - STEP * METERS_PER_DEGREE).abs() < 1.0);
+    }
+}